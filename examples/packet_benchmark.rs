@@ -0,0 +1,111 @@
+// camera rays through a field of spheres and a bumpy mesh, traced one by
+// one and then in packets of 4 and 8. The scene comes from a fixed seed so
+// runs can be compared, build it with
+//
+//     cargo run --release --example packet_benchmark
+use paprskomet::camera::Camera;
+use paprskomet::geometries::{Plane, Sphere};
+use paprskomet::mesh::Mesh;
+use paprskomet::packet::RayPacket;
+use paprskomet::ray::Ray;
+use paprskomet::transformations::{scaling, translation, view_transform};
+use paprskomet::vectors::{Point3, Vector3};
+use paprskomet::world::World;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::PI;
+use std::time::Instant;
+
+// every mode is timed this many times and the fastest run is reported, the
+// slower ones are mostly the machine doing something else
+const RUNS: usize = 5;
+
+fn main() {
+    let world = scene(&mut StdRng::seed_from_u64(47));
+
+    let mut camera = Camera::new(640, 480, PI / 3.);
+    camera.set_transform(view_transform(
+        Point3::new(0., 3., -8.),
+        Point3::new(0., 1., 10.),
+        Vector3::new(0., 1., 0.),
+    ));
+    let rays: Vec<Ray> = (0..camera.vsize)
+        .flat_map(|y| (0..camera.hsize).map(move |x| (x, y)))
+        .map(|(x, y)| camera.ray_for_pixel(x, y))
+        .collect();
+
+    measure("single rays", &rays, || {
+        rays.iter().filter(|&&r| world.hit(r).is_some()).count()
+    });
+
+    for width in [4, 8] {
+        let packets: Vec<RayPacket> = rays
+            .chunks(width)
+            .map(|chunk| RayPacket::new(chunk).unwrap())
+            .collect();
+        measure(&format!("packets of {}", width), &rays, || {
+            packets
+                .iter()
+                .flat_map(|p| world.hit_packet(p))
+                .filter(|h| h.is_some())
+                .count()
+        });
+    }
+}
+
+fn scene(rng: &mut StdRng) -> World {
+    let mut world = World::new();
+    world.add(Plane::new());
+    for _ in 0..2000 {
+        let mut s = Sphere::new();
+        let r = rng.gen::<f64>() * 0.4 + 0.1;
+        s.set_transform(
+            translation(
+                rng.gen::<f64>() * 40. - 20.,
+                rng.gen::<f64>() * 4. + 0.5,
+                rng.gen::<f64>() * 40.,
+            ) * scaling(r, r, r),
+        );
+        world.add(s);
+    }
+
+    let n = 100;
+    let mut positions = vec![];
+    for z in 0..=n {
+        for x in 0..=n {
+            let height = rng.gen::<f64>() * 0.3;
+            positions.push([x as f64 * 0.4 - 20., height, z as f64 * 0.4]);
+        }
+    }
+    let mut triangles = vec![];
+    for z in 0..n {
+        for x in 0..n {
+            let i = z * (n + 1) + x;
+            triangles.push([i, i + 1, i + n + 2]);
+            triangles.push([i, i + n + 2, i + n + 1]);
+        }
+    }
+    world.add(Mesh::new(positions, triangles).unwrap());
+    world.build_bvh();
+    world
+}
+
+fn measure<F>(name: &str, rays: &[Ray], trace: F)
+where
+    F: Fn() -> usize,
+{
+    let mut best = f64::INFINITY;
+    let mut hits = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        hits = trace();
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    println!(
+        "{}: {:.0} rays/s, {} of {} rays hit something",
+        name,
+        rays.len() as f64 / best,
+        hits,
+        rays.len()
+    );
+}
@@ -0,0 +1,149 @@
+use crate::colors::Color;
+use crate::intersections::{prepare_computations, Computations};
+use crate::lights::LightSample;
+use crate::ray::Ray;
+use crate::sampling::cosine_hemisphere;
+use crate::world::World;
+
+// darkens the ambient term where nearby geometry blocks the view of the
+// open sky, a lot cheaper than tracing full paths
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    pub samples: usize,
+    // anything further away than this doesn't occlude
+    pub max_distance: f64,
+}
+
+impl AmbientOcclusion {
+    pub fn new(samples: usize, max_distance: f64) -> AmbientOcclusion {
+        AmbientOcclusion {
+            samples,
+            max_distance,
+        }
+    }
+
+    // fraction of the hemisphere above the hit that is open, weighted by
+    // the cosine like the ambient light arriving from it would be
+    pub fn visibility(&self, world: &World, comps: &Computations) -> f64 {
+        if self.samples == 0 {
+            return 1.;
+        }
+
+        let open = (0..self.samples)
+            .filter(|_| {
+                let sample = LightSample {
+                    direction: cosine_hemisphere(
+                        comps.normalv,
+                        rand::random::<f64>(),
+                        rand::random::<f64>(),
+                    ),
+                    distance: self.max_distance,
+                };
+                !world.occluded(comps.over_point, sample, comps.time)
+            })
+            .count();
+
+        open as f64 / self.samples as f64
+    }
+
+    // visibility seen along the ray, rays that miss everything are open
+    pub fn visibility_at(&self, world: &World, ray: Ray) -> f64 {
+        match world.hit(ray) {
+            None => 1.,
+            Some(i) => {
+                let object = world.object(i.object_id).unwrap();
+                let comps = prepare_computations(i, ray, object);
+                self.visibility(world, &comps)
+            }
+        }
+    }
+
+    // the usual shading with the ambient term scaled by the visibility
+    pub fn color_at(&self, world: &World, ray: Ray) -> Color {
+        match world.hit(ray) {
+            None => world.background.color_in(ray.direction),
+            Some(i) => {
+                let object = world.object(i.object_id).unwrap();
+                let comps = prepare_computations(i, ray, object);
+
+                let mut m = *object.material();
+                m.ambient *= self.visibility(world, &comps);
+                world.shade_hit_with(&comps, m)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ambient_occlusion::AmbientOcclusion;
+    use crate::colors::Color;
+    use crate::geometries::Sphere;
+    use crate::ray::Ray;
+    use crate::transformations::scaling;
+    use crate::utils::float_compare;
+    use crate::vectors::{Point3, Vector3};
+    use crate::world::World;
+
+    fn ray() -> Ray {
+        Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.))
+    }
+
+    fn room() -> World {
+        // looking at the inside wall of a closed room, every direction ends
+        // on another wall
+        let mut w = World::default_world();
+        let mut room = Sphere::new();
+        room.set_transform(scaling(10., 10., 10.));
+        w.truncate(0);
+        w.add(room);
+        w
+    }
+
+    fn inside_ray() -> Ray {
+        Ray::new(Point3::new(0., 0., 0.), Vector3::new(0., 0., 1.))
+    }
+
+    #[test]
+    fn test_open_surface() {
+        let w = World::default_world();
+        let ao = AmbientOcclusion::new(16, 100.);
+
+        assert!(float_compare(ao.visibility_at(&w, ray()), 1.));
+        assert_eq!(ao.color_at(&w, ray()), w.color_at(ray()));
+    }
+
+    #[test]
+    fn test_miss_is_open() {
+        let w = World::default_world();
+        let ao = AmbientOcclusion::new(16, 100.);
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 1., 0.));
+
+        assert!(float_compare(ao.visibility_at(&w, r), 1.));
+        assert_eq!(ao.color_at(&w, r), Color::black());
+    }
+
+    #[test]
+    fn test_enclosed_surface() {
+        let w = room();
+        let ao = AmbientOcclusion::new(16, 100.);
+        assert!(float_compare(ao.visibility_at(&w, inside_ray()), 0.));
+
+        // only the ambient part goes away
+        let m = *w.objects()[0].material();
+        let ambient = m.color * m.ambient;
+        assert_eq!(
+            ao.color_at(&w, inside_ray()),
+            w.color_at(inside_ray()) - ambient
+        );
+    }
+
+    #[test]
+    fn test_max_distance() {
+        let w = room();
+
+        // the other walls are too far away to matter
+        let ao = AmbientOcclusion::new(16, 0.001);
+        assert!(float_compare(ao.visibility_at(&w, inside_ray()), 1.));
+    }
+}
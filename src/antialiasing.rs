@@ -0,0 +1,313 @@
+// where inside a pixel the camera rays go, offsets are in [0, 1) from the
+// pixel's top left corner
+#[derive(Debug, Clone, Copy)]
+pub enum Sampler {
+    // a single ray through the middle
+    Center,
+    // n by n evenly spaced rays
+    Grid(usize),
+    // one random spot in every cell of an n by n grid
+    Jittered(usize),
+    // the first n points of the base 2 and 3 halton sequence
+    Halton(usize),
+    // the first n points of the two dimensional sobol sequence
+    Sobol(usize),
+}
+
+impl Sampler {
+    // without a single ray every pixel would silently come out black
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Sampler::Grid(0)
+            | Sampler::Jittered(0)
+            | Sampler::Halton(0)
+            | Sampler::Sobol(0) => {
+                Err(format!("{:?} doesn't take any samples", self))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn offsets(&self) -> Vec<(f64, f64)> {
+        match *self {
+            Sampler::Center => vec![(0.5, 0.5)],
+            Sampler::Grid(n) => {
+                let mut offsets = vec![];
+                for j in 0..n {
+                    for i in 0..n {
+                        offsets.push((
+                            (i as f64 + 0.5) / n as f64,
+                            (j as f64 + 0.5) / n as f64,
+                        ));
+                    }
+                }
+                offsets
+            }
+            Sampler::Jittered(n) => {
+                let mut offsets = vec![];
+                for j in 0..n {
+                    for i in 0..n {
+                        offsets.push((
+                            (i as f64 + rand::random::<f64>()) / n as f64,
+                            (j as f64 + rand::random::<f64>()) / n as f64,
+                        ));
+                    }
+                }
+                offsets
+            }
+            // the sequence starts with 0, skipping it keeps the first
+            // sample off the corner
+            Sampler::Halton(n) => (1..=n)
+                .map(|i| (radical_inverse(i, 2), radical_inverse(i, 3)))
+                .collect(),
+            Sampler::Sobol(n) => (0..n as u32).map(sobol).collect(),
+        }
+    }
+}
+
+// renders with the camera's sampler first and then sends the extra rays
+// through the pixels that are noisy or stand out from their neighbours
+#[derive(Debug, Clone, Copy)]
+pub struct Adaptive {
+    pub sampler: Sampler,
+    // deviation of the samples, or difference to a neighbouring pixel,
+    // above which a pixel gets refined
+    pub threshold: f64,
+}
+
+// mirrors the digits of i in the given base around the decimal point
+fn radical_inverse(i: usize, base: usize) -> f64 {
+    let mut i = i;
+    let mut result = 0.;
+    let mut f = 1. / base as f64;
+
+    while i > 0 {
+        result += (i % base) as f64 * f;
+        i /= base;
+        f /= base as f64;
+    }
+    result
+}
+
+fn sobol(i: u32) -> (f64, f64) {
+    // the first dimension is the base 2 radical inverse, the second one
+    // uses the direction numbers of the (0, 2) sequence
+    let x = i.reverse_bits();
+
+    let mut y = 0_u32;
+    let mut v = 1_u32 << 31;
+    let mut i = i;
+    while i != 0 {
+        if i & 1 == 1 {
+            y ^= v;
+        }
+        i >>= 1;
+        v ^= v >> 1;
+    }
+
+    let scale = 1. / (1_u64 << 32) as f64;
+    (x as f64 * scale, y as f64 * scale)
+}
+
+// how much a sample counts towards a pixel depending on how far it is
+// from the pixel's center, distances are in pixels
+#[derive(Debug, Clone, Copy)]
+pub enum Filter {
+    // every sample inside the pixel counts the same, nothing outside
+    Box,
+    Tent { radius: f64 },
+    Gaussian { radius: f64, alpha: f64 },
+    // mitchell and netravali's cubic, b = c = 1/3 is what they recommend
+    Mitchell { radius: f64, b: f64, c: f64 },
+}
+
+impl Filter {
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Filter::Box => 0.5,
+            Filter::Tent { radius }
+            | Filter::Gaussian { radius, .. }
+            | Filter::Mitchell { radius, .. } => radius,
+        }
+    }
+
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+
+    fn weight_1d(&self, x: f64) -> f64 {
+        match *self {
+            // half open so a sample right on the edge only counts once
+            Filter::Box => {
+                if x > -0.5 && x <= 0.5 {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            Filter::Tent { radius } => (1. - x.abs() / radius).max(0.),
+            Filter::Gaussian { radius, alpha } => {
+                let g = |x: f64| (-alpha * x * x).exp();
+                (g(x) - g(radius)).max(0.)
+            }
+            Filter::Mitchell { radius, b, c } => {
+                mitchell(2. * x / radius, b, c)
+            }
+        }
+    }
+}
+
+fn mitchell(x: f64, b: f64, c: f64) -> f64 {
+    let x = x.abs();
+
+    if x < 1. {
+        ((12. - 9. * b - 6. * c) * x.powi(3)
+            + (-18. + 12. * b + 6. * c) * x.powi(2)
+            + (6. - 2. * b))
+            / 6.
+    } else if x < 2. {
+        ((-b - 6. * c) * x.powi(3)
+            + (6. * b + 30. * c) * x.powi(2)
+            + (-12. * b - 48. * c) * x
+            + (8. * b + 24. * c))
+            / 6.
+    } else {
+        0.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::antialiasing::{Filter, Sampler};
+    use crate::utils::float_compare;
+
+    fn inside(offsets: &[(f64, f64)]) -> bool {
+        offsets
+            .iter()
+            .all(|&(x, y)| (0. ..1.).contains(&x) && (0. ..1.).contains(&y))
+    }
+
+    #[test]
+    fn test_center() {
+        assert_eq!(Sampler::Center.offsets(), vec![(0.5, 0.5)]);
+    }
+
+    #[test]
+    fn test_grid() {
+        let offsets = Sampler::Grid(2).offsets();
+        assert_eq!(
+            offsets,
+            vec![(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)]
+        );
+    }
+
+    #[test]
+    fn test_jittered_is_stratified() {
+        let offsets = Sampler::Jittered(4).offsets();
+        assert_eq!(offsets.len(), 16);
+        assert!(inside(&offsets));
+
+        // exactly one sample in every cell
+        for j in 0..4 {
+            for i in 0..4 {
+                let cell = offsets
+                    .iter()
+                    .filter(|&&(x, y)| {
+                        (x * 4.) as usize == i && (y * 4.) as usize == j
+                    })
+                    .count();
+                assert_eq!(cell, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_halton() {
+        let offsets = Sampler::Halton(3).offsets();
+        let expected = [(0.5, 1. / 3.), (0.25, 2. / 3.), (0.75, 1. / 9.)];
+
+        for (o, e) in offsets.iter().zip(expected.iter()) {
+            assert!(float_compare(o.0, e.0));
+            assert!(float_compare(o.1, e.1));
+        }
+        assert!(inside(&Sampler::Halton(64).offsets()));
+    }
+
+    #[test]
+    fn test_sobol() {
+        let offsets = Sampler::Sobol(4).offsets();
+        assert_eq!(
+            offsets,
+            vec![(0., 0.), (0.5, 0.5), (0.25, 0.75), (0.75, 0.25)]
+        );
+
+        // the first 16 points put one sample in every cell of a 4 by 4 grid
+        let offsets = Sampler::Sobol(16).offsets();
+        assert!(inside(&offsets));
+        for j in 0..4 {
+            for i in 0..4 {
+                assert!(offsets.iter().any(|&(x, y)| {
+                    (x * 4.) as usize == i && (y * 4.) as usize == j
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Sampler::Center.validate().is_ok());
+        assert!(Sampler::Grid(1).validate().is_ok());
+        for sampler in [
+            Sampler::Grid(0),
+            Sampler::Jittered(0),
+            Sampler::Halton(0),
+            Sampler::Sobol(0),
+        ] {
+            assert!(sampler.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_box_filter() {
+        let f = Filter::Box;
+        assert!(float_compare(f.weight(0., 0.), 1.));
+        assert!(float_compare(f.weight(0.5, -0.3), 1.));
+        assert!(float_compare(f.weight(-0.5, 0.), 0.));
+        assert!(float_compare(f.weight(0.7, 0.), 0.));
+    }
+
+    #[test]
+    fn test_tent_filter() {
+        let f = Filter::Tent { radius: 1. };
+        assert!(float_compare(f.weight(0., 0.), 1.));
+        assert!(float_compare(f.weight(0.5, 0.), 0.5));
+        assert!(float_compare(f.weight(0.5, 0.5), 0.25));
+        assert!(float_compare(f.weight(1., 0.), 0.));
+    }
+
+    #[test]
+    fn test_gaussian_filter() {
+        let f = Filter::Gaussian {
+            radius: 1.5,
+            alpha: 2.,
+        };
+        assert!(f.weight(0., 0.) > f.weight(0.5, 0.));
+        assert!(f.weight(0.5, 0.) > f.weight(1., 0.));
+        assert!(float_compare(f.weight(1.5, 0.), 0.));
+        assert!(float_compare(f.weight(2., 0.), 0.));
+    }
+
+    #[test]
+    fn test_mitchell_filter() {
+        let f = Filter::Mitchell {
+            radius: 2.,
+            b: 1. / 3.,
+            c: 1. / 3.,
+        };
+        assert!(float_compare(f.weight(0., 0.), (8. / 9.) * (8. / 9.)));
+        // the negative lobe that keeps edges sharp
+        assert!(f.weight(1.5, 0.) < 0.);
+        assert!(float_compare(f.weight(2., 0.), 0.));
+        assert_eq!(f.radius(), 2.);
+    }
+}
@@ -0,0 +1,339 @@
+use crate::matrix::M;
+use crate::ray::Ray;
+use crate::vectors::Point3;
+
+// axis aligned box, infinite shapes get infinite sides
+#[derive(Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl BoundingBox {
+    pub fn new(min: Point3, max: Point3) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    // contains nothing, merging anything into it gives that thing back
+    pub fn empty() -> BoundingBox {
+        BoundingBox {
+            min: Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point3::new(
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ),
+        }
+    }
+
+    pub fn infinite() -> BoundingBox {
+        BoundingBox {
+            min: Point3::new(
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+                f64::NEG_INFINITY,
+            ),
+            max: Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x
+            || self.min.y > self.max.y
+            || self.min.z > self.max.z
+    }
+
+    pub fn is_infinite(&self) -> bool {
+        [
+            self.min.x, self.min.y, self.min.z, self.max.x, self.max.y,
+            self.max.z,
+        ]
+        .iter()
+        .any(|c| c.is_infinite())
+            && !self.is_empty()
+    }
+
+    pub fn add_point(&mut self, p: Point3) {
+        self.min = Point3::new(
+            self.min.x.min(p.x),
+            self.min.y.min(p.y),
+            self.min.z.min(p.z),
+        );
+        self.max = Point3::new(
+            self.max.x.max(p.x),
+            self.max.y.max(p.y),
+            self.max.z.max(p.z),
+        );
+    }
+
+    pub fn merge(&self, other: &BoundingBox) -> BoundingBox {
+        let mut merged = *self;
+        if !other.is_empty() {
+            merged.add_point(other.min);
+            merged.add_point(other.max);
+        }
+        merged
+    }
+
+    pub fn contains_point(&self, p: Point3) -> bool {
+        (self.min.x..=self.max.x).contains(&p.x)
+            && (self.min.y..=self.max.y).contains(&p.y)
+            && (self.min.z..=self.max.z).contains(&p.z)
+    }
+
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        other.is_empty()
+            || self.contains_point(other.min) && self.contains_point(other.max)
+    }
+
+    // box around all 8 corners after the transformation
+    pub fn transform(&self, m: &M) -> BoundingBox {
+        if self.is_empty() {
+            return *self;
+        }
+
+        let mut transformed = BoundingBox::empty();
+        for &x in &[self.min.x, self.max.x] {
+            for &y in &[self.min.y, self.max.y] {
+                for &z in &[self.min.z, self.max.z] {
+                    transformed.add_point(transform_corner(m, [x, y, z]));
+                }
+            }
+        }
+        transformed
+    }
+
+    // slab test, only says whether the ray goes through the box at all
+    pub fn intersects(&self, ray: Ray) -> bool {
+        self.hit_range(ray).is_some()
+    }
+
+    // distances along the ray where it enters and leaves the box, the
+    // entry is negative when the ray starts inside
+    pub fn hit_range(&self, ray: Ray) -> Option<(f64, f64)> {
+        let (x_min, x_max) =
+            slab(ray.origin.x, ray.direction.x, self.min.x, self.max.x);
+        let (y_min, y_max) =
+            slab(ray.origin.y, ray.direction.y, self.min.y, self.max.y);
+        let (z_min, z_max) =
+            slab(ray.origin.z, ray.direction.z, self.min.z, self.max.z);
+
+        let t_min = x_min.max(y_min).max(z_min);
+        let t_max = x_max.min(y_max).min(z_max);
+        if t_min <= t_max && t_max >= 0. {
+            Some((t_min, t_max))
+        } else {
+            None
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        Point3::new(
+            (self.min.x + self.max.x) / 2.,
+            (self.min.y + self.max.y) / 2.,
+            (self.min.z + self.max.z) / 2.,
+        )
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.;
+        }
+        let d = self.max - self.min;
+        2. * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+}
+
+// where the ray is between the two planes of one axis
+fn slab(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+    // parallel rays are either always or never between the planes
+    if direction == 0. {
+        return if origin >= min && origin <= max {
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            (f64::INFINITY, f64::NEG_INFINITY)
+        };
+    }
+
+    let t1 = (min - origin) / direction;
+    let t2 = (max - origin) / direction;
+    if t1 > t2 {
+        (t2, t1)
+    } else {
+        (t1, t2)
+    }
+}
+
+// zeros in the matrix must not turn infinite corners into NaN
+fn transform_corner(m: &M, corner: [f64; 3]) -> Point3 {
+    let row = |i: usize| {
+        let mut sum = m.get(i, 3);
+        for (j, c) in corner.iter().enumerate() {
+            let factor = m.get(i, j);
+            if factor != 0. {
+                sum += factor * c;
+            }
+        }
+        sum
+    };
+    Point3::new(row(0), row(1), row(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounds::BoundingBox;
+    use crate::ray::Ray;
+    use crate::transformations::{rotation_x, rotation_y, translation};
+    use crate::vectors::{Point3, Vector3};
+    use std::f64::consts::{PI, SQRT_2};
+
+    fn unit() -> BoundingBox {
+        BoundingBox::new(Point3::new(-1., -1., -1.), Point3::new(1., 1., 1.))
+    }
+
+    #[test]
+    fn test_empty_box() {
+        let mut b = BoundingBox::empty();
+        assert!(b.is_empty());
+        assert!(!b.is_infinite());
+        assert!(!b.contains_point(Point3::new(0., 0., 0.)));
+
+        b.add_point(Point3::new(-5., 2., 0.));
+        b.add_point(Point3::new(7., 0., -3.));
+        assert_eq!(b.min, Point3::new(-5., 0., -3.));
+        assert_eq!(b.max, Point3::new(7., 2., 0.));
+    }
+
+    #[test]
+    fn test_merge() {
+        let a = BoundingBox::new(
+            Point3::new(-5., -2., 0.),
+            Point3::new(7., 4., 4.),
+        );
+        let b = BoundingBox::new(
+            Point3::new(8., -7., -2.),
+            Point3::new(14., 2., 8.),
+        );
+
+        let merged = a.merge(&b);
+        assert_eq!(merged.min, Point3::new(-5., -7., -2.));
+        assert_eq!(merged.max, Point3::new(14., 4., 8.));
+
+        let with_empty = a.merge(&BoundingBox::empty());
+        assert_eq!(with_empty.min, a.min);
+        assert_eq!(with_empty.max, a.max);
+    }
+
+    #[test]
+    fn test_contains() {
+        let b = BoundingBox::new(
+            Point3::new(5., -2., 0.),
+            Point3::new(11., 4., 7.),
+        );
+
+        assert!(b.contains_point(Point3::new(5., -2., 0.)));
+        assert!(b.contains_point(Point3::new(8., 1., 3.)));
+        assert!(!b.contains_point(Point3::new(3., 0., 3.)));
+        assert!(!b.contains_point(Point3::new(8., -4., 3.)));
+        assert!(!b.contains_point(Point3::new(8., 1., 8.)));
+
+        assert!(b.contains_box(&BoundingBox::new(
+            Point3::new(6., -1., 1.),
+            Point3::new(10., 3., 6.),
+        )));
+        assert!(!b.contains_box(&BoundingBox::new(
+            Point3::new(4., -3., -1.),
+            Point3::new(10., 3., 6.),
+        )));
+        assert!(BoundingBox::infinite().contains_box(&b));
+    }
+
+    #[test]
+    fn test_transform() {
+        let b = unit().transform(&(rotation_x(PI / 4.) * rotation_y(PI / 4.)));
+
+        assert_eq!(b.min, Point3::new(-SQRT_2, -1.70711, -1.70711));
+        assert_eq!(b.max, Point3::new(SQRT_2, 1.70711, 1.70711));
+
+        let moved = unit().transform(&translation(1., 2., 3.));
+        assert_eq!(moved.min, Point3::new(0., 1., 2.));
+        assert_eq!(moved.max, Point3::new(2., 3., 4.));
+    }
+
+    #[test]
+    fn test_transform_infinite() {
+        let plane = BoundingBox::new(
+            Point3::new(f64::NEG_INFINITY, 0., f64::NEG_INFINITY),
+            Point3::new(f64::INFINITY, 0., f64::INFINITY),
+        );
+
+        // moving it up keeps it flat and endless
+        let moved = plane.transform(&translation(0., 2., 0.));
+        assert!(moved.is_infinite());
+        assert_eq!(moved.min.y, 2.);
+        assert_eq!(moved.max.y, 2.);
+        assert!(!moved.min.x.is_nan() && !moved.max.z.is_nan());
+
+        // standing up it spans y instead
+        let wall = plane.transform(&rotation_x(PI / 2.));
+        assert_eq!(wall.min.y, f64::NEG_INFINITY);
+        assert_eq!(wall.max.y, f64::INFINITY);
+    }
+
+    #[test]
+    fn test_ray_intersects_box() {
+        let b = unit();
+        let cases = [
+            (Point3::new(5., 0.5, 0.), Vector3::new(-1., 0., 0.), true),
+            (Point3::new(-5., 0.5, 0.), Vector3::new(1., 0., 0.), true),
+            (Point3::new(0.5, 5., 0.), Vector3::new(0., -1., 0.), true),
+            (Point3::new(0., 0.5, 0.), Vector3::new(0., 0., 1.), true),
+            (Point3::new(-2., 0., 0.), Vector3::new(2., 4., 6.), false),
+            (Point3::new(0., -2., 0.), Vector3::new(6., 2., 4.), false),
+            (Point3::new(2., 0., 2.), Vector3::new(0., 0., -1.), false),
+            (Point3::new(2., 2., 0.), Vector3::new(-1., 0., 0.), false),
+            // pointing away from it
+            (Point3::new(5., 0., 0.), Vector3::new(1., 0., 0.), false),
+        ];
+
+        for (origin, direction, expected) in cases {
+            let r = Ray::new(origin, direction.normalize());
+            assert_eq!(b.intersects(r), expected);
+        }
+    }
+
+    #[test]
+    fn test_ray_intersects_infinite_box() {
+        let floor = BoundingBox::new(
+            Point3::new(f64::NEG_INFINITY, -1., f64::NEG_INFINITY),
+            Point3::new(f64::INFINITY, 0., f64::INFINITY),
+        );
+
+        let down =
+            Ray::new(Point3::new(100., 5., -30.), Vector3::new(0., -1., 0.));
+        let along =
+            Ray::new(Point3::new(0., 5., 0.), Vector3::new(1., 0., 0.));
+        assert!(floor.intersects(down));
+        assert!(!floor.intersects(along));
+        assert!(!BoundingBox::empty().intersects(down));
+    }
+
+    #[test]
+    fn test_hit_range() {
+        let r = Ray::new(Point3::new(-5., 0., 0.), Vector3::new(1., 0., 0.));
+        assert_eq!(unit().hit_range(r), Some((4., 6.)));
+
+        let inside =
+            Ray::new(Point3::new(0., 0., 0.), Vector3::new(0., 1., 0.));
+        assert_eq!(unit().hit_range(inside), Some((-1., 1.)));
+    }
+
+    #[test]
+    fn test_centroid_and_area() {
+        let b =
+            BoundingBox::new(Point3::new(0., 0., 0.), Point3::new(1., 2., 3.));
+        assert_eq!(b.centroid(), Point3::new(0.5, 1., 1.5));
+        assert_eq!(b.surface_area(), 22.);
+        assert_eq!(BoundingBox::empty().surface_area(), 0.);
+    }
+}
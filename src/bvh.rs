@@ -0,0 +1,579 @@
+use crate::bounds::BoundingBox;
+use crate::packet::{count, single, Mask, RayPacket, LANES};
+use crate::ray::Ray;
+use crate::vectors::Point3;
+
+// leaves never get bigger than this
+const MAX_LEAF: usize = 4;
+const BINS: usize = 12;
+
+// bounding volume hierarchy over anything that has a bounding box. The
+// tree is stored depth first in one array, the first child of a node comes
+// right after it and the second one is found through `offset`
+pub struct Bvh {
+    primitives: usize,
+    nodes: Vec<Node>,
+    // primitives in the order the leaves refer to them
+    indices: Vec<usize>,
+    // primitives with infinite bounds, tested by every ray
+    unbounded: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    bounds: BoundingBox,
+    // first primitive for a leaf, second child for an interior node
+    offset: usize,
+    // 0 for interior nodes
+    count: usize,
+    axis: usize,
+}
+
+impl Bvh {
+    // split with the surface area heuristic evaluated on a few bins along
+    // the longest axis, the same index in `bounds` identifies a primitive
+    // in every query
+    pub fn build(bounds: &[BoundingBox]) -> Bvh {
+        let mut finite = vec![];
+        let mut unbounded = vec![];
+        for (i, b) in bounds.iter().enumerate() {
+            if b.is_infinite() {
+                unbounded.push(i);
+            } else if !b.is_empty() {
+                finite.push(i);
+            }
+        }
+
+        let mut bvh = Bvh {
+            primitives: bounds.len(),
+            nodes: vec![],
+            indices: vec![],
+            unbounded,
+        };
+        if !finite.is_empty() {
+            bvh.build_node(bounds, &mut finite);
+        }
+        bvh
+    }
+
+    fn build_node(&mut self, bounds: &[BoundingBox], prims: &mut [usize]) {
+        let node_bounds = prims
+            .iter()
+            .fold(BoundingBox::empty(), |b, &i| b.merge(&bounds[i]));
+        let mut centroids = BoundingBox::empty();
+        for &i in prims.iter() {
+            centroids.add_point(bounds[i].centroid());
+        }
+
+        let axis = longest_axis(&centroids);
+        let (low, high) = (
+            coordinate(centroids.min, axis),
+            coordinate(centroids.max, axis),
+        );
+
+        let split = if prims.len() <= MAX_LEAF || high <= low {
+            None
+        } else {
+            best_split(bounds, prims, axis, low, high, &node_bounds)
+        };
+
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            offset: self.indices.len(),
+            count: prims.len(),
+            axis,
+        });
+
+        let bin = match split {
+            Some(bin) => bin,
+            None => {
+                self.indices.extend_from_slice(prims);
+                return;
+            }
+        };
+
+        let mid = partition(prims, |&i| {
+            bin_of(coordinate(bounds[i].centroid(), axis), low, high) <= bin
+        });
+
+        let (left, right) = prims.split_at_mut(mid);
+        self.build_node(bounds, left);
+        let second = self.nodes.len();
+        self.build_node(bounds, right);
+
+        self.nodes[index].offset = second;
+        self.nodes[index].count = 0;
+    }
+
+    // how many bounds it was built from
+    pub fn primitives(&self) -> usize {
+        self.primitives
+    }
+
+    // calls f for every primitive whose leaf the ray goes through
+    pub fn candidates<F>(&self, ray: Ray, mut f: F)
+    where
+        F: FnMut(usize),
+    {
+        self.unbounded.iter().for_each(|&i| f(i));
+        self.walk(ray, f64::INFINITY, |i, _| {
+            f(i);
+            None
+        });
+    }
+
+    // stops at the first primitive f says is in the way, enough for
+    // shadow rays that don't care which one it is
+    pub fn any<F>(&self, ray: Ray, max_distance: f64, mut f: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        if self.unbounded.iter().any(|&i| f(i)) {
+            return true;
+        }
+
+        let mut found = false;
+        self.walk(ray, max_distance, |i, _| {
+            if f(i) {
+                found = true;
+                Some(f64::NEG_INFINITY)
+            } else {
+                None
+            }
+        });
+        found
+    }
+
+    // nearest hit, f gives the distance to the primitive if the ray hits
+    // it before the given limit. Boxes further away than the best hit so far
+    // are skipped
+    pub fn closest<F>(&self, ray: Ray, mut f: F) -> Option<(usize, f64)>
+    where
+        F: FnMut(usize, f64) -> Option<f64>,
+    {
+        let mut best: Option<(usize, f64)> = None;
+        for &i in &self.unbounded {
+            let limit = best.map_or(f64::INFINITY, |b| b.1);
+            if let Some(t) = f(i, limit) {
+                if t < limit {
+                    best = Some((i, t));
+                }
+            }
+        }
+
+        let limit = best.map_or(f64::INFINITY, |b| b.1);
+        self.walk(ray, limit, |i, limit| match f(i, limit) {
+            Some(t) if t < limit => {
+                best = Some((i, t));
+                Some(t)
+            }
+            _ => None,
+        });
+        best
+    }
+
+    // visits the leaves front to back, visit can shrink how far along the
+    // ray anything still matters by returning the new limit
+    fn walk<F>(&self, ray: Ray, max_distance: f64, visit: F)
+    where
+        F: FnMut(usize, f64) -> Option<f64>,
+    {
+        if !self.nodes.is_empty() {
+            self.walk_from(0, ray, max_distance, visit);
+        }
+    }
+
+    fn walk_from<F>(
+        &self,
+        start: usize,
+        ray: Ray,
+        max_distance: f64,
+        mut visit: F,
+    ) where
+        F: FnMut(usize, f64) -> Option<f64>,
+    {
+        let negative = [
+            ray.direction.x < 0.,
+            ray.direction.y < 0.,
+            ray.direction.z < 0.,
+        ];
+        let mut limit = max_distance;
+        let mut stack = Stack::new();
+        stack.push(start);
+
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            match node.bounds.hit_range(ray) {
+                Some((entry, _)) if entry <= limit => {}
+                _ => continue,
+            }
+
+            if node.count > 0 {
+                for &i in &self.indices[node.offset..node.offset + node.count]
+                {
+                    if let Some(l) = visit(i, limit) {
+                        limit = l;
+                    }
+                    // nothing can be closer than that, the search is over
+                    if limit == f64::NEG_INFINITY {
+                        return;
+                    }
+                }
+                continue;
+            }
+
+            // the child closer to the ray's origin goes on top
+            if negative[node.axis] {
+                stack.push(n + 1);
+                stack.push(node.offset);
+            } else {
+                stack.push(node.offset);
+                stack.push(n + 1);
+            }
+        }
+    }
+
+    // nearest hits for the lanes of a packet in the mask. The lanes go down
+    // the tree together while enough of them agree on where to go, once
+    // most of them have dropped out the rest carry on one ray at a time.
+    // visit gets the lanes that reached a primitive and lowers their limits
+    // when it finds something closer
+    pub fn closest_packet<F>(
+        &self,
+        packet: &RayPacket,
+        mask: Mask,
+        limit: &mut [f64; LANES],
+        mut visit: F,
+    ) where
+        F: FnMut(usize, Mask, &mut [f64; LANES]),
+    {
+        for &i in &self.unbounded {
+            visit(i, mask, limit);
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack = Stack::new();
+        stack.push(0);
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            let active = packet.hits_box(&node.bounds, mask, limit);
+            let remaining = count(&active);
+            if remaining == 0 {
+                continue;
+            }
+
+            if remaining * 2 < count(&mask) {
+                for l in (0..LANES).filter(|&l| active[l]) {
+                    self.walk_from(n, packet.ray(l), limit[l], |i, _| {
+                        visit(i, single(l), limit);
+                        Some(limit[l])
+                    });
+                }
+                continue;
+            }
+
+            if node.count > 0 {
+                for &i in &self.indices[node.offset..node.offset + node.count]
+                {
+                    visit(i, active, limit);
+                }
+                continue;
+            }
+
+            // ordered by the first lane, the others mostly go the same way
+            let first = active.iter().position(|&a| a).unwrap();
+            if packet.direction[node.axis][first] < 0. {
+                stack.push(n + 1);
+                stack.push(node.offset);
+            } else {
+                stack.push(node.offset);
+                stack.push(n + 1);
+            }
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        fn depth_of(nodes: &[Node], n: usize) -> usize {
+            if nodes[n].count > 0 {
+                1
+            } else {
+                1 + depth_of(nodes, n + 1)
+                    .max(depth_of(nodes, nodes[n].offset))
+            }
+        }
+
+        if self.nodes.is_empty() {
+            0
+        } else {
+            depth_of(&self.nodes, 0)
+        }
+    }
+}
+
+// nodes still to visit, kept on the stack of the thread so a ray doesn't
+// allocate. A tree deeper than the array would spill into the vec, the
+// builder's trees never get close
+struct Stack {
+    nodes: [usize; 64],
+    len: usize,
+    spilled: Vec<usize>,
+}
+
+impl Stack {
+    fn new() -> Stack {
+        Stack {
+            nodes: [0; 64],
+            len: 0,
+            spilled: vec![],
+        }
+    }
+
+    fn push(&mut self, n: usize) {
+        if self.len < self.nodes.len() {
+            self.nodes[self.len] = n;
+            self.len += 1;
+        } else {
+            self.spilled.push(n);
+        }
+    }
+
+    // the spilled nodes were pushed last, so they come off first
+    fn pop(&mut self) -> Option<usize> {
+        if let Some(n) = self.spilled.pop() {
+            return Some(n);
+        }
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.nodes[self.len])
+    }
+}
+
+// bin with the lowest cost to split after, none if keeping the leaf is
+// cheaper
+fn best_split(
+    bounds: &[BoundingBox],
+    prims: &[usize],
+    axis: usize,
+    low: f64,
+    high: f64,
+    node_bounds: &BoundingBox,
+) -> Option<usize> {
+    let mut counts = [0; BINS];
+    let mut boxes = [BoundingBox::empty(); BINS];
+    for &i in prims {
+        let b = bin_of(coordinate(bounds[i].centroid(), axis), low, high);
+        counts[b] += 1;
+        boxes[b] = boxes[b].merge(&bounds[i]);
+    }
+
+    let mut best: Option<(usize, f64)> = None;
+    for split in 0..BINS - 1 {
+        let (mut left, mut right) =
+            (BoundingBox::empty(), BoundingBox::empty());
+        let (mut n_left, mut n_right) = (0, 0);
+        for b in 0..BINS {
+            if b <= split {
+                left = left.merge(&boxes[b]);
+                n_left += counts[b];
+            } else {
+                right = right.merge(&boxes[b]);
+                n_right += counts[b];
+            }
+        }
+        if n_left == 0 || n_right == 0 {
+            continue;
+        }
+
+        let cost = left.surface_area() * n_left as f64
+            + right.surface_area() * n_right as f64;
+        if best.is_none_or(|(_, c)| cost < c) {
+            best = Some((split, cost));
+        }
+    }
+
+    // walking into the children costs about as much as testing one more
+    // primitive. Boxes without any area can't be compared, split those
+    let (split, cost) = best?;
+    let area = node_bounds.surface_area();
+    if area > 0. && 1. + cost / area >= prims.len() as f64 {
+        None
+    } else {
+        Some(split)
+    }
+}
+
+fn bin_of(c: f64, low: f64, high: f64) -> usize {
+    let b = ((c - low) / (high - low) * BINS as f64) as usize;
+    b.min(BINS - 1)
+}
+
+fn longest_axis(b: &BoundingBox) -> usize {
+    let d = b.max - b.min;
+    if d.x >= d.y && d.x >= d.z {
+        0
+    } else if d.y >= d.z {
+        1
+    } else {
+        2
+    }
+}
+
+fn coordinate(p: Point3, axis: usize) -> f64 {
+    match axis {
+        0 => p.x,
+        1 => p.y,
+        _ => p.z,
+    }
+}
+
+// moves everything matching to the front, gives back how many there are
+fn partition<F>(items: &mut [usize], keep: F) -> usize
+where
+    F: Fn(&usize) -> bool,
+{
+    let mut mid = 0;
+    for i in 0..items.len() {
+        if keep(&items[i]) {
+            items.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounds::BoundingBox;
+    use crate::bvh::{Bvh, Stack};
+    use crate::fixtures;
+    use crate::ray::Ray;
+    use crate::vectors::{Point3, Vector3};
+    use rand::rngs::StdRng;
+    use rand::Rng;
+
+    fn random_boxes(rng: &mut StdRng, n: usize) -> Vec<BoundingBox> {
+        (0..n)
+            .map(|_| {
+                let c = fixtures::point_in(
+                    rng,
+                    Point3::new(-10., -10., -10.),
+                    Point3::new(10., 10., 10.),
+                );
+                let size = rng.gen::<f64>() * 0.5;
+                let d = Vector3::new(size, size, size);
+                BoundingBox::new(c - d, c + d)
+            })
+            .collect()
+    }
+
+    fn random_ray(rng: &mut StdRng) -> Ray {
+        fixtures::ray_forward(
+            rng,
+            Point3::new(-15., -15., -20.),
+            Point3::new(15., 15., -20.),
+        )
+    }
+
+    #[test]
+    fn test_empty_bvh() {
+        let bvh = Bvh::build(&[]);
+        let mut visited = 0;
+        bvh.candidates(random_ray(&mut fixtures::rng()), |_| visited += 1);
+        assert_eq!(visited, 0);
+        assert_eq!(bvh.depth(), 0);
+    }
+
+    #[test]
+    fn test_bvh_splits() {
+        let bvh = Bvh::build(&random_boxes(&mut fixtures::rng(), 1000));
+        assert!(bvh.depth() > 3);
+        assert!(bvh.depth() < 40);
+    }
+
+    #[test]
+    fn test_candidates_cover_every_hit() {
+        let mut rng = fixtures::rng();
+        let boxes = random_boxes(&mut rng, 500);
+        let bvh = Bvh::build(&boxes);
+
+        for _ in 0..200 {
+            let r = random_ray(&mut rng);
+            let mut candidates = vec![];
+            bvh.candidates(r, |i| candidates.push(i));
+
+            for (i, b) in boxes.iter().enumerate() {
+                if b.intersects(r) {
+                    assert!(candidates.contains(&i));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_closest_matches_brute_force() {
+        let mut rng = fixtures::rng();
+        let boxes = random_boxes(&mut rng, 500);
+        let bvh = Bvh::build(&boxes);
+        let entry = |b: &BoundingBox, r: Ray| {
+            b.hit_range(r).map(|(t, _)| t).filter(|&t| t >= 0.)
+        };
+
+        for _ in 0..200 {
+            let r = random_ray(&mut rng);
+            let expected = boxes
+                .iter()
+                .enumerate()
+                .filter_map(|(i, b)| entry(b, r).map(|t| (i, t)))
+                .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+            let found = bvh.closest(r, |i, limit| {
+                entry(&boxes[i], r).filter(|&t| t < limit)
+            });
+            assert_eq!(found.map(|f| f.1), expected.map(|e| e.1));
+        }
+    }
+
+    #[test]
+    fn test_any() {
+        let mut rng = fixtures::rng();
+        let boxes = random_boxes(&mut rng, 300);
+        let bvh = Bvh::build(&boxes);
+
+        for _ in 0..100 {
+            let r = random_ray(&mut rng);
+            let expected = boxes.iter().any(|b| b.intersects(r));
+            assert_eq!(
+                bvh.any(r, f64::INFINITY, |i| boxes[i].intersects(r)),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_stack_spills_in_order() {
+        let mut stack = Stack::new();
+        for n in 0..100 {
+            stack.push(n);
+        }
+        let popped: Vec<usize> = std::iter::from_fn(|| stack.pop()).collect();
+        assert_eq!(popped, (0..100).rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_unbounded_always_visited() {
+        let mut boxes = random_boxes(&mut fixtures::rng(), 10);
+        boxes.push(BoundingBox::infinite());
+        let bvh = Bvh::build(&boxes);
+
+        let away =
+            Ray::new(Point3::new(0., 100., 0.), Vector3::new(0., 1., 0.));
+        let mut candidates = vec![];
+        bvh.candidates(away, |i| candidates.push(i));
+        assert!(candidates.contains(&10));
+    }
+}
@@ -0,0 +1,874 @@
+use crate::ambient_occlusion::AmbientOcclusion;
+use crate::antialiasing::{Adaptive, Filter, Sampler};
+use crate::canvas::Canvas;
+use crate::colors::Color;
+use crate::matrix::M;
+use crate::packet::{RayPacket, LANES};
+use crate::path_tracer::PathTracer;
+use crate::ray::Ray;
+use crate::sampling::{concentric_disk, regular_polygon};
+use crate::vectors::Point3;
+use crate::world::World;
+
+// how the color seen along a camera ray gets worked out
+#[derive(Debug, Clone, Copy)]
+pub enum Integrator {
+    // local lighting from the world's light
+    Whitted,
+    PathTracer(PathTracer),
+    // whitted shading with the ambient term darkened in the crevices
+    AmbientOcclusion(AmbientOcclusion),
+}
+
+// shape of the lens opening, out of focus highlights take this shape
+#[derive(Debug, Clone, Copy)]
+pub enum Aperture {
+    Disk,
+    // like the blades of a real diaphragm, needs at least 3
+    Polygon { blades: usize, rotation: f64 },
+}
+
+pub struct Camera {
+    pub hsize: usize,
+    pub vsize: usize,
+    pub field_of_view: f64,
+    pub integrator: Integrator,
+    sampler: Sampler,
+    pub filter: Filter,
+    // more rays for the pixels that look like they need them
+    adaptive: Option<Adaptive>,
+    // radius of the lens, 0 is a pinhole with everything in focus
+    pub aperture: f64,
+    aperture_shape: Aperture,
+    // distance in front of the camera that is in focus
+    pub focal_distance: f64,
+    // moments the shutter opens and closes, every ray gets a random time
+    // in between so moving objects blur along their path
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    // camera rays traced together, up to LANES. Only plain whitted renders
+    // without the adaptive pass use packets, 1 traces every ray alone
+    pub packet_width: usize,
+    transform: M,
+    transform_inv: M,
+
+    half_width: f64,
+    half_height: f64,
+    pub pixel_size: f64,
+}
+
+impl Camera {
+    pub fn new(hsize: usize, vsize: usize, field_of_view: f64) -> Camera {
+        let half_view = (field_of_view / 2.).tan();
+        let aspect = hsize as f64 / vsize as f64;
+
+        let (half_width, half_height) = if aspect >= 1. {
+            (half_view, half_view / aspect)
+        } else {
+            (half_view * aspect, half_view)
+        };
+
+        Camera {
+            hsize,
+            vsize,
+            field_of_view,
+            integrator: Integrator::Whitted,
+            sampler: Sampler::Center,
+            filter: Filter::Box,
+            adaptive: None,
+            aperture: 0.,
+            aperture_shape: Aperture::Disk,
+            focal_distance: 1.,
+            shutter_open: 0.,
+            shutter_close: 0.,
+            packet_width: 1,
+            transform: M::ident(4),
+            transform_inv: M::ident(4),
+            half_width,
+            half_height,
+            pixel_size: half_width * 2. / hsize as f64,
+        }
+    }
+
+    pub fn set_transform(&mut self, m: M) {
+        self.transform_inv = m.inverse();
+        self.transform = m;
+    }
+
+    pub fn transform(&self) -> &M {
+        &self.transform
+    }
+
+    pub fn set_sampler(&mut self, sampler: Sampler) -> Result<(), String> {
+        sampler.validate()?;
+        self.sampler = sampler;
+        Ok(())
+    }
+
+    pub fn set_adaptive(
+        &mut self,
+        adaptive: Option<Adaptive>,
+    ) -> Result<(), String> {
+        if let Some(a) = adaptive {
+            a.sampler.validate()?;
+        }
+        self.adaptive = adaptive;
+        Ok(())
+    }
+
+    pub fn set_aperture_shape(
+        &mut self,
+        shape: Aperture,
+    ) -> Result<(), String> {
+        if let Aperture::Polygon { blades, .. } = shape {
+            if blades < 3 {
+                return Err(format!(
+                    "an aperture needs at least 3 blades, got {}",
+                    blades
+                ));
+            }
+        }
+        self.aperture_shape = shape;
+        Ok(())
+    }
+
+    pub fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+        self.ray_through(px as f64 + 0.5, py as f64 + 0.5)
+    }
+
+    // ray through any spot on the canvas, pixel (x, y) covers the square
+    // from (x, y) to (x + 1, y + 1). With an aperture every call starts
+    // from a different spot on the lens, so the blur gets smoother with
+    // more antialiasing samples
+    pub fn ray_through(&self, x: f64, y: f64) -> Ray {
+        let world_x = self.half_width - x * self.pixel_size;
+        let world_y = self.half_height - y * self.pixel_size;
+
+        if self.aperture <= 0. {
+            let pixel =
+                &self.transform_inv * Point3::new(world_x, world_y, -1.);
+            let origin = &self.transform_inv * Point3::new(0., 0., 0.);
+            return Ray::new(origin, (pixel - origin).normalize())
+                .at_time(self.shutter_time());
+        }
+
+        // everything on the focal plane is sharp, whichever part of the
+        // lens the ray starts from
+        let f = self.focal_distance;
+        let focus =
+            &self.transform_inv * Point3::new(world_x * f, world_y * f, -f);
+
+        let (lx, ly) = self.lens_sample();
+        let origin = &self.transform_inv
+            * Point3::new(lx * self.aperture, ly * self.aperture, 0.);
+        Ray::new(origin, (focus - origin).normalize())
+            .at_time(self.shutter_time())
+    }
+
+    fn shutter_time(&self) -> f64 {
+        if self.shutter_close <= self.shutter_open {
+            return self.shutter_open;
+        }
+        let length = self.shutter_close - self.shutter_open;
+        self.shutter_open + rand::random::<f64>() * length
+    }
+
+    fn lens_sample(&self) -> (f64, f64) {
+        match self.aperture_shape {
+            Aperture::Polygon { blades, rotation } => regular_polygon(
+                blades,
+                rotation,
+                rand::random::<f64>(),
+                rand::random::<f64>(),
+                rand::random::<f64>(),
+            ),
+            Aperture::Disk => {
+                concentric_disk(rand::random::<f64>(), rand::random::<f64>())
+            }
+        }
+    }
+
+    // color seen along a single camera ray
+    pub fn color_along(&self, world: &World, ray: Ray) -> Color {
+        match self.integrator {
+            Integrator::Whitted => world.color_at(ray),
+            Integrator::PathTracer(pt) => pt.radiance(world, ray),
+            Integrator::AmbientOcclusion(ao) => ao.color_at(world, ray),
+        }
+    }
+
+    pub fn render(&self, world: &World) -> Canvas {
+        let whitted = matches!(self.integrator, Integrator::Whitted);
+        if whitted && self.adaptive.is_none() && self.packet_width > 1 {
+            return self.render_packets(world);
+        }
+        self.render_with(|ray| self.color_along(world, ray)).0
+    }
+
+    // the image together with a map of how many rays every pixel got,
+    // white for the most
+    pub fn render_with_heat_map(&self, world: &World) -> (Canvas, Canvas) {
+        let (image, counts) =
+            self.render_with(|ray| self.color_along(world, ray));
+
+        let most = counts.iter().copied().max().unwrap_or(0).max(1);
+        let mut heat_map = Canvas::new(self.hsize, self.vsize);
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let v = counts[y * self.hsize + x] as f64 / most as f64;
+                heat_map.write_pixel(x, y, Color::new(v, v, v));
+            }
+        }
+        (image, heat_map)
+    }
+
+    // just the occlusion in gray, white is fully open, for compositing
+    // over a render done some other way
+    pub fn render_ambient_occlusion(
+        &self,
+        world: &World,
+        ao: &AmbientOcclusion,
+    ) -> Canvas {
+        self.render_with(|ray| {
+            let v = ao.visibility_at(world, ray);
+            Color::new(v, v, v)
+        })
+        .0
+    }
+
+    // shoots the sampler's rays through every pixel and spreads each
+    // result over the pixels the filter reaches, also gives back the
+    // number of rays every pixel got
+    fn render_with<F>(&self, color: F) -> (Canvas, Vec<usize>)
+    where
+        F: Fn(Ray) -> Color,
+    {
+        let size = self.hsize * self.vsize;
+        let mut sums = vec![Color::black(); size];
+        let mut weights = vec![0.; size];
+        let mut counts = vec![0; size];
+        let mut means = vec![0.; size];
+        let mut deviations = vec![0.; size];
+
+        let mut trace = |x: usize, y: usize, sampler: &Sampler| {
+            let mut values = vec![];
+            for (sx, sy) in self.spots(x, y, sampler) {
+                let c = color(self.ray_through(sx, sy));
+                self.splat(&mut sums, &mut weights, sx, sy, c);
+                values.push(c.max_component());
+            }
+            values
+        };
+
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let values = trace(x, y, &self.sampler);
+                let i = y * self.hsize + x;
+                counts[i] = values.len();
+                means[i] = mean(&values);
+                deviations[i] = deviation(&values, means[i]);
+            }
+        }
+
+        if let Some(adaptive) = self.adaptive {
+            for y in 0..self.vsize {
+                for x in 0..self.hsize {
+                    let i = y * self.hsize + x;
+                    let contrast = self
+                        .neighbours(x, y)
+                        .map(|j| (means[i] - means[j]).abs())
+                        .fold(0., f64::max);
+
+                    if deviations[i] > adaptive.threshold
+                        || contrast > adaptive.threshold
+                    {
+                        counts[i] += trace(x, y, &adaptive.sampler).len();
+                    }
+                }
+            }
+        }
+
+        (self.resolve(&sums, &weights), counts)
+    }
+
+    // where in pixel (x, y) the rays go. Every pixel draws its own offsets,
+    // so jittered samples don't repeat the same pattern across the image.
+    // The path tracer traces all of its paths for each of the offsets,
+    // spread over the offset's share of the pixel so even a single offset
+    // in the middle gets antialiased
+    fn spots(&self, x: usize, y: usize, sampler: &Sampler) -> Vec<(f64, f64)> {
+        let offsets = sampler.offsets();
+        let (x, y) = (x as f64, y as f64);
+        let paths = match self.integrator {
+            Integrator::PathTracer(pt) => pt.samples_per_pixel.max(1),
+            _ => {
+                return offsets
+                    .iter()
+                    .map(|(ox, oy)| (x + ox, y + oy))
+                    .collect()
+            }
+        };
+
+        let cell = 1. / (offsets.len() as f64).sqrt();
+        let jitter = |s: f64, from: f64| {
+            (s + (rand::random::<f64>() - 0.5) * cell).clamp(from, from + 1.)
+        };
+
+        let mut spots = Vec::with_capacity(offsets.len() * paths);
+        for (ox, oy) in &offsets {
+            for _ in 0..paths {
+                spots.push((jitter(x + ox, x), jitter(y + oy, y)));
+            }
+        }
+        spots
+    }
+
+    // the same samples as render_with, but the rays of neighbouring pixels
+    // in a row look for their hits together
+    fn render_packets(&self, world: &World) -> Canvas {
+        let size = self.hsize * self.vsize;
+        let mut sums = vec![Color::black(); size];
+        let mut weights = vec![0.; size];
+        let width = self.packet_width.min(LANES);
+
+        for y in 0..self.vsize {
+            for from in (0..self.hsize).step_by(width) {
+                let to = (from + width).min(self.hsize);
+                let pixels: Vec<Vec<(f64, f64)>> = (from..to)
+                    .map(|x| self.spots(x, y, &self.sampler))
+                    .collect();
+                // every pixel has as many spots, the k-th ones of the
+                // pixels go together
+                for k in 0..pixels[0].len() {
+                    let spots: Vec<(f64, f64)> =
+                        pixels.iter().map(|p| p[k]).collect();
+                    let rays: Vec<Ray> = spots
+                        .iter()
+                        .map(|&(sx, sy)| self.ray_through(sx, sy))
+                        .collect();
+                    let hits =
+                        world.hit_packet(&RayPacket::new(&rays).unwrap());
+
+                    for (l, &(sx, sy)) in spots.iter().enumerate() {
+                        let c = world.color_for_hit(rays[l], hits[l]);
+                        self.splat(&mut sums, &mut weights, sx, sy, c);
+                    }
+                }
+            }
+        }
+        self.resolve(&sums, &weights)
+    }
+
+    fn resolve(&self, sums: &[Color], weights: &[f64]) -> Canvas {
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let i = y * self.hsize + x;
+                if weights[i] != 0. {
+                    image.write_pixel(x, y, sums[i] / weights[i]);
+                }
+            }
+        }
+        image
+    }
+
+    // indices of the pixels right above, below and to the sides
+    fn neighbours(&self, x: usize, y: usize) -> impl Iterator<Item = usize> {
+        let (w, h) = (self.hsize, self.vsize);
+        let mut around = vec![];
+        if x > 0 {
+            around.push(y * w + x - 1);
+        }
+        if x + 1 < w {
+            around.push(y * w + x + 1);
+        }
+        if y > 0 {
+            around.push((y - 1) * w + x);
+        }
+        if y + 1 < h {
+            around.push((y + 1) * w + x);
+        }
+        around.into_iter()
+    }
+
+    fn splat(
+        &self,
+        sums: &mut [Color],
+        weights: &mut [f64],
+        sx: f64,
+        sy: f64,
+        color: Color,
+    ) {
+        let radius = self.filter.radius();
+        let range = |s: f64, size: usize| {
+            let from = (s - 0.5 - radius).ceil().max(0.) as usize;
+            let to = ((s - 0.5 + radius).floor() + 1.).clamp(0., size as f64);
+            from..to as usize
+        };
+
+        for y in range(sy, self.vsize) {
+            for x in range(sx, self.hsize) {
+                let w = self
+                    .filter
+                    .weight(x as f64 + 0.5 - sx, y as f64 + 0.5 - sy);
+                if w != 0. {
+                    let i = y * self.hsize + x;
+                    sums[i] = sums[i] + color * w;
+                    weights[i] += w;
+                }
+            }
+        }
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+fn deviation(values: &[f64], mean: f64) -> f64 {
+    let squares: f64 = values.iter().map(|v| (v - mean).powi(2)).sum();
+    (squares / values.len().max(1) as f64).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ambient_occlusion::AmbientOcclusion;
+    use crate::antialiasing::{Adaptive, Filter, Sampler};
+    use crate::camera::{Aperture, Camera, Integrator};
+    use crate::canvas::Canvas;
+    use crate::colors::Color;
+    use crate::geometries::Sphere;
+    use crate::matrix::M;
+    use crate::path_tracer::PathTracer;
+    use crate::transformations::{
+        rotation_y, scaling, translation, view_transform,
+    };
+    use crate::utils::float_compare;
+    use crate::vectors::{Point3, Vector3};
+    use crate::world::World;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_new_camera() {
+        let c = Camera::new(160, 120, PI / 2.);
+        assert_eq!(c.hsize, 160);
+        assert_eq!(c.vsize, 120);
+        assert!(float_compare(c.field_of_view, PI / 2.));
+        assert_eq!(*c.transform(), M::ident(4));
+    }
+
+    #[test]
+    fn test_pixel_size() {
+        let horizontal = Camera::new(200, 125, PI / 2.);
+        assert!(float_compare(horizontal.pixel_size, 0.01));
+
+        let vertical = Camera::new(125, 200, PI / 2.);
+        assert!(float_compare(vertical.pixel_size, 0.01));
+    }
+
+    #[test]
+    fn test_ray_through_center() {
+        let c = Camera::new(201, 101, PI / 2.);
+        let r = c.ray_for_pixel(100, 50);
+
+        assert_eq!(r.origin, Point3::new(0., 0., 0.));
+        assert_eq!(r.direction, Vector3::new(0., 0., -1.));
+    }
+
+    #[test]
+    fn test_ray_through_corner() {
+        let c = Camera::new(201, 101, PI / 2.);
+        let r = c.ray_for_pixel(0, 0);
+
+        assert_eq!(r.origin, Point3::new(0., 0., 0.));
+        assert_eq!(r.direction, Vector3::new(0.66519, 0.33259, -0.66851));
+    }
+
+    #[test]
+    fn test_ray_transformed_camera() {
+        let mut c = Camera::new(201, 101, PI / 2.);
+        c.set_transform(rotation_y(PI / 4.) * translation(0., -2., 5.));
+        let r = c.ray_for_pixel(100, 50);
+
+        let half = 2_f64.sqrt() / 2.;
+        assert_eq!(r.origin, Point3::new(0., 2., -5.));
+        assert_eq!(r.direction, Vector3::new(half, 0., -half));
+    }
+
+    #[test]
+    fn test_render() {
+        let w = World::default_world();
+        let mut c = Camera::new(11, 11, PI / 2.);
+        c.set_transform(view_transform(
+            Point3::new(0., 0., -5.),
+            Point3::new(0., 0., 0.),
+            Vector3::new(0., 1., 0.),
+        ));
+
+        let image = c.render(&w);
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_packet_render_matches() {
+        let mut w = World::default_world();
+        w.build_bvh();
+        let mut c = Camera::new(11, 9, PI / 2.);
+        c.set_sampler(Sampler::Grid(2)).unwrap();
+        c.set_transform(view_transform(
+            Point3::new(0., 1., -3.),
+            Point3::new(0., 0., 0.),
+            Vector3::new(0., 1., 0.),
+        ));
+        let expected = c.render(&w);
+
+        // 11 pixels a row leave a narrower packet at the end
+        for width in [4, 8] {
+            c.packet_width = width;
+            let image = c.render(&w);
+            for y in 0..c.vsize {
+                for x in 0..c.hsize {
+                    assert_eq!(image.pixel_at(x, y), expected.pixel_at(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_path_traced() {
+        let mut w = World::new();
+        let mut ball = Sphere::new();
+        ball.material.ambient = 0.;
+        ball.material.specular = 0.;
+        ball.material.diffuse = 0.5;
+        w.add(ball);
+
+        let mut room = Sphere::new();
+        room.set_transform(scaling(10., 10., 10.));
+        room.material.color = Color::black();
+        room.material.specular = 0.;
+        room.material.emissive = Color::new(1., 1., 1.);
+        w.add(room);
+
+        let mut c = Camera::new(11, 11, PI / 2.);
+        c.set_transform(view_transform(
+            Point3::new(0., 0., -5.),
+            Point3::new(0., 0., 0.),
+            Vector3::new(0., 1., 0.),
+        ));
+
+        // without a light the whitted shading leaves the ball black
+        assert_eq!(c.render(&w).pixel_at(5, 5), Color::black());
+
+        c.integrator = Integrator::PathTracer(PathTracer::new(4));
+        let image = c.render(&w);
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.5, 0.5, 0.5));
+        // the corners look straight at the glowing room
+        assert_eq!(image.pixel_at(0, 0), Color::new(1., 1., 1.));
+    }
+
+    #[test]
+    fn test_path_tracer_spots() {
+        let mut c = Camera::new(11, 11, PI / 2.);
+        assert_eq!(c.spots(3, 4, &Sampler::Center), vec![(3.5, 4.5)]);
+
+        // every path goes through a different spot of the pixel
+        c.integrator = Integrator::PathTracer(PathTracer::new(16));
+        let spots = c.spots(3, 4, &Sampler::Center);
+        assert_eq!(spots.len(), 16);
+        for &(sx, sy) in &spots {
+            assert!((3. ..=4.).contains(&sx) && (4. ..=5.).contains(&sy));
+        }
+        assert!(spots.iter().any(|&s| s != spots[0]));
+
+        // with more offsets the paths stay in the offset's own cell
+        let spots = c.spots(0, 0, &Sampler::Grid(2));
+        assert_eq!(spots.len(), 64);
+        for &(sx, sy) in &spots[..16] {
+            assert!(sx <= 0.5 && sy <= 0.5);
+        }
+    }
+
+    #[test]
+    fn test_jitter_differs_between_pixels() {
+        let c = Camera::new(11, 11, PI / 2.);
+        let within = |x: usize, y: usize| {
+            let spots = c.spots(x, y, &Sampler::Jittered(2));
+            let (x, y) = (x as f64, y as f64);
+            spots
+                .iter()
+                .map(|&(sx, sy)| (sx - x, sy - y))
+                .collect::<Vec<_>>()
+        };
+
+        // the same pattern in both would alias like a plain grid
+        assert_ne!(within(3, 4), within(4, 4));
+        assert_eq!(within(3, 4).len(), 4);
+    }
+
+    #[test]
+    fn test_samplers_without_rays_rejected() {
+        let mut c = Camera::new(11, 11, PI / 2.);
+        assert!(c.set_sampler(Sampler::Jittered(0)).is_err());
+        let empty = Adaptive {
+            sampler: Sampler::Halton(0),
+            threshold: 0.05,
+        };
+        assert!(c.set_adaptive(Some(empty)).is_err());
+
+        // the camera keeps what it had
+        assert!(matches!(c.sampler, Sampler::Center));
+        assert!(c.adaptive.is_none());
+    }
+
+    #[test]
+    fn test_ambient_occlusion_pass() {
+        let mut w = World::default_world();
+        let mut room = Sphere::new();
+        room.set_transform(scaling(10., 10., 10.));
+        w.add(room);
+
+        let mut c = Camera::new(11, 11, PI / 2.);
+        c.set_transform(view_transform(
+            Point3::new(0., 0., -5.),
+            Point3::new(0., 0., 0.),
+            Vector3::new(0., 1., 0.),
+        ));
+        let ao = AmbientOcclusion::new(16, 100.);
+
+        // the walls of the room are closed in, the ball in the middle
+        // sees those walls too
+        let pass = c.render_ambient_occlusion(&w, &ao);
+        assert_eq!(pass.pixel_at(0, 0), Color::black());
+        assert_eq!(pass.pixel_at(5, 5), Color::black());
+
+        // open space around the ball once the walls are gone
+        w.truncate(2);
+        let pass = c.render_ambient_occlusion(&w, &ao);
+        assert_eq!(pass.pixel_at(0, 0), Color::white());
+        assert_eq!(pass.pixel_at(5, 5), Color::white());
+
+        c.integrator = Integrator::AmbientOcclusion(ao);
+        assert_eq!(
+            c.render(&w).pixel_at(5, 5),
+            w.color_at(c.ray_for_pixel(5, 5))
+        );
+    }
+
+    fn glowing_ball() -> (World, Camera) {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.material.emissive = Color::white();
+        w.add(s);
+
+        let mut c = Camera::new(11, 11, PI / 6.);
+        c.set_transform(view_transform(
+            Point3::new(0., 0., -5.),
+            Point3::new(0., 0., 0.),
+            Vector3::new(0., 1., 0.),
+        ));
+        (w, c)
+    }
+
+    fn edge_pixels(image: &Canvas) -> usize {
+        let mut count = 0;
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let v = image.pixel_at(x, y).max_component();
+                if v > 0.01 && v < 0.99 {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn test_single_sample_is_aliased() {
+        let (w, c) = glowing_ball();
+        assert_eq!(edge_pixels(&c.render(&w)), 0);
+    }
+
+    #[test]
+    fn test_supersampling_smooths_edges() {
+        let (w, mut c) = glowing_ball();
+
+        for sampler in [
+            Sampler::Grid(4),
+            Sampler::Jittered(4),
+            Sampler::Halton(16),
+            Sampler::Sobol(16),
+        ] {
+            c.set_sampler(sampler).unwrap();
+            let image = c.render(&w);
+            assert!(edge_pixels(&image) > 0);
+            // the middle of the ball stays solid
+            assert_eq!(image.pixel_at(5, 5), Color::white());
+            assert_eq!(image.pixel_at(0, 0), Color::black());
+        }
+    }
+
+    #[test]
+    fn test_wide_filters() {
+        let (w, mut c) = glowing_ball();
+        c.set_sampler(Sampler::Grid(2)).unwrap();
+
+        for filter in [
+            Filter::Tent { radius: 1.5 },
+            Filter::Gaussian {
+                radius: 1.5,
+                alpha: 2.,
+            },
+            Filter::Mitchell {
+                radius: 2.,
+                b: 1. / 3.,
+                c: 1. / 3.,
+            },
+        ] {
+            c.filter = filter;
+            let image = c.render(&w);
+            assert!(edge_pixels(&image) > 0);
+            assert_eq!(image.pixel_at(5, 5), Color::white());
+        }
+    }
+
+    #[test]
+    fn test_adaptive_refines_edges() {
+        let (w, mut c) = glowing_ball();
+        c.set_sampler(Sampler::Grid(2)).unwrap();
+        c.set_adaptive(Some(Adaptive {
+            sampler: Sampler::Jittered(4),
+            threshold: 0.05,
+        }))
+        .unwrap();
+
+        let (image, heat_map) = c.render_with_heat_map(&w);
+        assert!(edge_pixels(&image) > 0);
+        assert_eq!(image.pixel_at(5, 5), Color::white());
+
+        // flat parts keep the first 4 rays out of 20
+        let flat = Color::new(0.2, 0.2, 0.2);
+        assert_eq!(heat_map.pixel_at(5, 5), flat);
+        assert_eq!(heat_map.pixel_at(0, 0), flat);
+
+        let mut refined = 0;
+        for y in 0..11 {
+            for x in 0..11 {
+                if heat_map.pixel_at(x, y) == Color::white() {
+                    refined += 1;
+                }
+            }
+        }
+        assert!(refined > 0 && refined < 121);
+    }
+
+    #[test]
+    fn test_adaptive_skips_flat_image() {
+        let (mut w, mut c) = glowing_ball();
+        w.truncate(0);
+        c.set_adaptive(Some(Adaptive {
+            sampler: Sampler::Grid(4),
+            threshold: 0.05,
+        }))
+        .unwrap();
+
+        // nothing stands out so every pixel keeps its single ray
+        let (_, heat_map) = c.render_with_heat_map(&w);
+        assert_eq!(heat_map.pixel_at(3, 7), Color::white());
+        assert_eq!(heat_map.pixel_at(10, 10), Color::white());
+    }
+
+    #[test]
+    fn test_lens_rays_meet_at_focus() {
+        let mut c = Camera::new(201, 101, PI / 2.);
+        c.set_transform(translation(0., 0., 5.));
+        c.aperture = 0.5;
+        c.focal_distance = 3.;
+
+        for shape in [
+            Aperture::Disk,
+            Aperture::Polygon {
+                blades: 5,
+                rotation: 0.2,
+            },
+        ] {
+            c.set_aperture_shape(shape).unwrap();
+            let mut origins = vec![];
+
+            for _ in 0..20 {
+                let r = c.ray_for_pixel(100, 50);
+                let lens = r.origin - Point3::new(0., 0., -5.);
+                assert!(float_compare(lens.z, 0.));
+                assert!(lens.magnitude() <= 0.5 + 1e-9);
+
+                // 3 units in front of the camera everything lines up
+                let t = 3. / -r.direction.z;
+                assert_eq!(r.position(t), Point3::new(0., 0., -8.));
+                origins.push(r.origin);
+            }
+            assert!(origins.iter().any(|&o| o != origins[0]));
+        }
+    }
+
+    #[test]
+    fn test_aperture_needs_three_blades() {
+        let mut c = Camera::new(11, 11, PI / 2.);
+        for blades in 0..3 {
+            let shape = Aperture::Polygon {
+                blades,
+                rotation: 0.,
+            };
+            assert!(c.set_aperture_shape(shape).is_err());
+        }
+        assert!(matches!(c.aperture_shape, Aperture::Disk));
+    }
+
+    #[test]
+    fn test_depth_of_field() {
+        let (w, mut c) = glowing_ball();
+        c.set_sampler(Sampler::Jittered(4)).unwrap();
+        c.aperture = 0.3;
+
+        // the ball's front is 4 units away
+        c.focal_distance = 4.;
+        let sharp = c.render(&w);
+
+        c.focal_distance = 1.;
+        let blurred = c.render(&w);
+
+        assert!(edge_pixels(&blurred) > edge_pixels(&sharp));
+        assert_eq!(sharp.pixel_at(5, 5), Color::white());
+    }
+
+    #[test]
+    fn test_shutter_times() {
+        let mut c = Camera::new(11, 11, PI / 2.);
+        assert_eq!(c.ray_for_pixel(5, 5).time, 0.);
+
+        c.shutter_open = 0.25;
+        c.shutter_close = 0.75;
+        for _ in 0..20 {
+            let t = c.ray_for_pixel(5, 5).time;
+            assert!((0.25..=0.75).contains(&t));
+        }
+    }
+
+    #[test]
+    fn test_motion_blur() {
+        let (mut w, mut c) = glowing_ball();
+        c.set_sampler(Sampler::Jittered(4)).unwrap();
+        let still = c.render(&w);
+
+        let mut s = Sphere::new();
+        s.material.emissive = Color::white();
+        s.set_motion(translation(-0.5, 0., 0.), translation(0.5, 0., 0.));
+        w.truncate(0);
+        w.add(s);
+        c.shutter_close = 1.;
+        let moving = c.render(&w);
+
+        // smeared sideways but not up and down
+        assert!(edge_pixels(&moving) > edge_pixels(&still));
+        assert_eq!(moving.pixel_at(5, 0), still.pixel_at(5, 0));
+    }
+}
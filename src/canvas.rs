@@ -1,5 +1,5 @@
 use crate::colors::Color;
-use crate::vectors::Tuple;
+use crate::vectors::Point3;
 use std::fs;
 
 #[derive(Debug, Clone)]
@@ -13,8 +13,8 @@ pub struct Canvas {
 impl Canvas {
     pub fn new(width: usize, height: usize) -> Self {
         Canvas {
-            width: width,
-            height: height,
+            width,
+            height,
             pixels: vec![vec![Color::black(); width]; height],
         }
     }
@@ -34,6 +34,16 @@ impl Canvas {
         self.pixels[row][x] = color;
     }
 
+    // unlike write, these go from the top left corner like the rows of
+    // the image do, which is how the camera walks through its pixels
+    pub fn write_pixel(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y][x] = color;
+    }
+
+    pub fn pixel_at(&self, x: usize, y: usize) -> Color {
+        self.pixels[y][x]
+    }
+
     pub fn write_origin(&mut self, x: isize, y: isize, color: Color) {
         // writes pixel referenced by midpoint of the canvas...
 
@@ -43,7 +53,7 @@ impl Canvas {
         self.write((mid_x + x) as usize, (mid_y + y) as usize, color);
     }
 
-    pub fn write_point(&mut self, p: Tuple, color: Color) {
+    pub fn write_point(&mut self, p: Point3, color: Color) {
         let x = p.x as isize;
         let y = p.y as isize;
 
@@ -88,6 +98,17 @@ mod tests {
         assert_eq!(Color::red(), canvas.pixels[1][1])
     }
 
+    #[test]
+    fn test_write_pixel() {
+        let mut canvas = Canvas::new(10, 20);
+        canvas.write_pixel(2, 3, Color::red());
+        assert_eq!(canvas.pixel_at(2, 3), Color::red());
+
+        // write counts rows from the bottom
+        canvas.write(4, 0, Color::white());
+        assert_eq!(canvas.pixel_at(4, 19), Color::white());
+    }
+
     //
     // #[test]
     // fn test_ppm() {
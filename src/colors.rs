@@ -1,16 +1,17 @@
+use crate::scalar::Scalar;
 use crate::utils::float_compare;
 use std::ops::{Add, Div, Mul, Sub};
 
 #[derive(Debug, Clone, Copy)]
-pub struct Color {
-    r: f64,
-    g: f64,
-    b: f64,
+pub struct Color<T = f64> {
+    r: T,
+    g: T,
+    b: T,
 }
 
-fn f_pixel(n: f64) -> usize {
-    // we store pixels in f64 but for ppm format we need them as integers..
-    let x = n * 255.0;
+fn f_pixel<T: Scalar>(n: T) -> usize {
+    // we store pixels as floats but for ppm format we need them as integers..
+    let x = n.to_f64() * 255.0;
     let mut int_x = x as usize;
 
     if int_x > 255 {
@@ -19,24 +20,32 @@ fn f_pixel(n: f64) -> usize {
     int_x
 }
 
-impl Color {
-    pub fn new(r: f64, g: f64, b: f64) -> Self {
+impl<T: Scalar> Color<T> {
+    pub fn new(r: T, g: T, b: T) -> Self {
         Self { r, g, b }
     }
 
     pub fn black() -> Self {
         Self {
-            r: 0.0,
-            g: 0.0,
-            b: 0.0,
+            r: T::ZERO,
+            g: T::ZERO,
+            b: T::ZERO,
+        }
+    }
+
+    pub fn white() -> Self {
+        Self {
+            r: T::ONE,
+            g: T::ONE,
+            b: T::ONE,
         }
     }
 
     pub fn red() -> Self {
         Self {
-            r: 1.0,
-            g: 0.0,
-            b: 0.0,
+            r: T::ONE,
+            g: T::ZERO,
+            b: T::ZERO,
         }
     }
 
@@ -49,8 +58,8 @@ impl Color {
         )
     }
 
-    pub fn magnitude(&self) -> f64 {
-        (self.r.powi(2) + self.g.powi(2) + self.b.powi(2)).sqrt()
+    pub fn magnitude(&self) -> T {
+        (self.r * self.r + self.g * self.g + self.b * self.b).sqrt()
     }
 
     pub fn normalize(&self) -> Self {
@@ -62,12 +71,25 @@ impl Color {
         }
     }
 
-    pub fn dot(&self, rhs: &Self) -> f64 {
+    pub fn dot(&self, rhs: &Self) -> T {
         self.r * rhs.r + self.g * rhs.g + self.b * rhs.b
     }
+
+    pub fn max_component(&self) -> T {
+        self.r.max(self.g).max(self.b)
+    }
+
+    // the same color in another precision
+    pub fn cast<U: Scalar>(&self) -> Color<U> {
+        Color::new(
+            U::from_f64(self.r.to_f64()),
+            U::from_f64(self.g.to_f64()),
+            U::from_f64(self.b.to_f64()),
+        )
+    }
 }
 
-impl PartialEq for Color {
+impl<T: Scalar> PartialEq for Color<T> {
     fn eq(&self, other: &Self) -> bool {
         float_compare(self.r, other.r)
             && float_compare(self.g, other.g)
@@ -75,136 +97,153 @@ impl PartialEq for Color {
     }
 }
 
-impl Add for Color {
-    type Output = Color;
+impl<T: Scalar> Add for Color<T> {
+    type Output = Color<T>;
 
     fn add(self, rhs: Self) -> Self::Output {
         Color::new(self.r + rhs.r, self.g + rhs.g, self.b + rhs.b)
     }
 }
 
-impl Sub for Color {
-    type Output = Color;
+impl<T: Scalar> Sub for Color<T> {
+    type Output = Color<T>;
 
     fn sub(self, rhs: Self) -> Self::Output {
         Color::new(self.r - rhs.r, self.g - rhs.g, self.b - rhs.b)
     }
 }
 
-impl Mul<Color> for f64 {
-    type Output = Color;
+// like for vectors, a scalar on the left needs an impl per precision
+macro_rules! scalar_times_color {
+    ($t:ty) => {
+        impl Mul<Color<$t>> for $t {
+            type Output = Color<$t>;
 
-    fn mul(self, rhs: Color) -> Self::Output {
-        Color::new(self * rhs.r, self * rhs.g, self * rhs.b)
-    }
+            fn mul(self, rhs: Color<$t>) -> Self::Output {
+                Color::new(self * rhs.r, self * rhs.g, self * rhs.b)
+            }
+        }
+    };
 }
 
-impl Mul<f64> for Color {
-    type Output = Color;
+scalar_times_color!(f64);
+scalar_times_color!(f32);
 
-    fn mul(self, rhs: f64) -> Self::Output {
+impl<T: Scalar> Mul<T> for Color<T> {
+    type Output = Color<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
         Color::new(self.r * rhs, self.g * rhs, self.b * rhs)
     }
 }
 
-impl Div<f64> for Color {
-    type Output = Color;
+impl<T: Scalar> Div<T> for Color<T> {
+    type Output = Color<T>;
 
-    fn div(self, rhs: f64) -> Self::Output {
+    fn div(self, rhs: T) -> Self::Output {
         Color::new(self.r / rhs, self.g / rhs, self.b / rhs)
     }
 }
 
-impl Mul<Color> for Color {
-    type Output = Color;
+impl<T: Scalar> Mul<Color<T>> for Color<T> {
+    type Output = Color<T>;
 
-    fn mul(self, rhs: Color) -> Self::Output {
+    fn mul(self, rhs: Color<T>) -> Self::Output {
         Color::new(self.r * rhs.r, self.g * rhs.g, self.b * rhs.b)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::colors::Color;
-    use crate::utils::float_compare;
+    crate::scalar::for_each_precision! {
+        use crate::utils::float_compare;
 
-    #[test]
-    fn test_colors() {
-        let color = Color::new(1.0, 2.0, 3.0);
-        assert_eq!(color.r, 1.0);
-        assert_eq!(color.b, 3.0);
-    }
+        type Color = crate::colors::Color<Float>;
 
-    #[test]
-    fn test_compare() {
-        let color_a = Color::new(1.0, 2.0, 3.0);
-        let color_b = Color::new(1.0, 2.0, 3.0);
-        assert_eq!(color_a, color_b);
-    }
+        #[test]
+        fn test_colors() {
+            let color = Color::new(1.0, 2.0, 3.0);
+            assert_eq!(color.r, 1.0);
+            assert_eq!(color.b, 3.0);
+        }
 
-    #[test]
-    fn test_add_colors() {
-        let color_a = Color::new(1.0, 2.0, 3.0);
-        let color_b = Color::new(2.0, 3.0, 5.0);
+        #[test]
+        fn test_compare() {
+            let color_a = Color::new(1.0, 2.0, 3.0);
+            let color_b = Color::new(1.0, 2.0, 3.0);
+            assert_eq!(color_a, color_b);
+        }
 
-        let color_sum = Color::new(3.0, 5.0, 8.0);
+        #[test]
+        fn test_add_colors() {
+            let color_a = Color::new(1.0, 2.0, 3.0);
+            let color_b = Color::new(2.0, 3.0, 5.0);
 
-        assert_eq!(color_a + color_b, color_sum);
-    }
+            let color_sum = Color::new(3.0, 5.0, 8.0);
 
-    #[test]
-    fn test_subtract_vectors() {
-        let color_a = Color::new(1.0, 2.0, 3.0);
-        let color_b = Color::new(2.0, 3.0, 5.0);
+            assert_eq!(color_a + color_b, color_sum);
+        }
 
-        let color_sum = Color::new(-1.0, -1.0, -2.0);
+        #[test]
+        fn test_subtract_vectors() {
+            let color_a = Color::new(1.0, 2.0, 3.0);
+            let color_b = Color::new(2.0, 3.0, 5.0);
 
-        assert_eq!(color_a - color_b, color_sum);
-    }
+            let color_sum = Color::new(-1.0, -1.0, -2.0);
 
-    #[test]
-    fn test_scalar_multiplication() {
-        let color_a = Color::new(1.0, 2.0, 3.0);
-        let result = Color::new(3.0, 6.0, 9.0);
+            assert_eq!(color_a - color_b, color_sum);
+        }
 
-        assert_eq!(3.0 * color_a, result);
-        assert_eq!(color_a * 3.0, result);
-    }
+        #[test]
+        fn test_scalar_multiplication() {
+            let color_a = Color::new(1.0, 2.0, 3.0);
+            let result = Color::new(3.0, 6.0, 9.0);
 
-    #[test]
-    fn test_scalar_div() {
-        let color_a = Color::new(3.0, 6.0, 9.0);
-        let result = Color::new(1.0, 2.0, 3.0);
+            assert_eq!(3.0 * color_a, result);
+            assert_eq!(color_a * 3.0, result);
+        }
 
-        assert_eq!(color_a / 3.0, result);
-    }
+        #[test]
+        fn test_scalar_div() {
+            let color_a = Color::new(3.0, 6.0, 9.0);
+            let result = Color::new(1.0, 2.0, 3.0);
 
-    #[test]
-    fn test_magnitude() {
-        let color_a = Color::new(3.0, 6.0, 9.0);
-        assert!(float_compare(color_a.magnitude(), 11.224972));
-    }
+            assert_eq!(color_a / 3.0, result);
+        }
 
-    #[test]
-    fn test_normalization() {
-        let color_a = Color::new(3.0, 6.0, 9.0);
-        assert_eq!(color_a.normalize().magnitude(), 1.0);
-    }
+        #[test]
+        fn test_magnitude() {
+            let color_a = Color::new(3.0, 6.0, 9.0);
+            assert!(float_compare(color_a.magnitude(), 11.224972));
+        }
 
-    #[test]
-    fn test_dot() {
-        let color_a = Color::new(1.0, 2.0, 3.0);
-        let color_b = Color::new(2.0, 3.0, 4.0);
-        assert_eq!(color_a.dot(&color_b), 20.0);
-    }
+        #[test]
+        fn test_normalization() {
+            let color_a = Color::new(3.0, 6.0, 9.0);
+            assert_eq!(color_a.normalize().magnitude(), 1.0);
+        }
+
+        #[test]
+        fn test_dot() {
+            let color_a = Color::new(1.0, 2.0, 3.0);
+            let color_b = Color::new(2.0, 3.0, 4.0);
+            assert_eq!(color_a.dot(&color_b), 20.0);
+        }
+
+        #[test]
+        fn test_max_component() {
+            let color_a = Color::new(0.3, 1.2, 0.7);
+            assert!(float_compare(color_a.max_component(), 1.2));
+        }
 
-    #[test]
-    fn test_color_product() {
-        let color_a = Color::new(1.0, 0.2, 1.0);
-        let color_b = Color::new(0.9, 1.0, 2.0);
+        #[test]
+        fn test_color_product() {
+            let color_a = Color::new(1.0, 0.2, 1.0);
+            let color_b = Color::new(0.9, 1.0, 2.0);
 
-        let color_c = Color::new(0.9, 0.2, 2.0);
+            let color_c = Color::new(0.9, 0.2, 2.0);
 
-        assert_eq!(color_a * color_b, color_c);
+            assert_eq!(color_a * color_b, color_c);
+        }
     }
 }
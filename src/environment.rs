@@ -0,0 +1,383 @@
+use crate::colors::Color;
+use crate::sky::Sky;
+use crate::vectors::Vector3;
+use std::f64::consts::PI;
+use std::fs;
+
+// whatever a ray sees when it doesn't hit any object
+pub enum Background {
+    Color(Color),
+    // simple procedural sky, blends from the horizon up to the zenith and
+    // from the horizon down to the ground
+    Gradient {
+        zenith: Color,
+        horizon: Color,
+        ground: Color,
+    },
+    Map(EnvironmentMap),
+    Sky(Sky),
+}
+
+impl Background {
+    pub fn color_in(&self, direction: Vector3) -> Color {
+        match self {
+            Background::Color(c) => *c,
+            Background::Gradient {
+                zenith,
+                horizon,
+                ground,
+            } => {
+                let y = direction.normalize().y;
+                if y >= 0. {
+                    *horizon * (1. - y) + *zenith * y
+                } else {
+                    *horizon * (1. + y) + *ground * -y
+                }
+            }
+            Background::Map(map) => map.color_in(direction),
+            Background::Sky(sky) => sky.color_in(direction),
+        }
+    }
+}
+
+// latitude-longitude image wrapped around the whole scene, +y is the top
+// row and looking down -z ends up in the middle of the image
+pub struct EnvironmentMap {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<Color>,
+}
+
+impl EnvironmentMap {
+    // pixels go row by row starting in the top left corner
+    pub fn new(
+        width: usize,
+        height: usize,
+        pixels: Vec<Color>,
+    ) -> Result<EnvironmentMap, String> {
+        if width == 0 || height == 0 {
+            return Err("Cant have empty environment map!".into());
+        }
+
+        if width.checked_mul(height) != Some(pixels.len()) {
+            return Err("Illegal environment map size".into());
+        }
+
+        Ok(EnvironmentMap {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn from_hdr(filename: &str) -> Result<EnvironmentMap, String> {
+        let data = fs::read(filename).map_err(|e| e.to_string())?;
+        EnvironmentMap::from_hdr_bytes(&data)
+    }
+
+    // reads the radiance .hdr (rgbe) format, both flat and run length
+    // encoded scanlines
+    pub fn from_hdr_bytes(data: &[u8]) -> Result<EnvironmentMap, String> {
+        let mut pos = 0;
+        let mut lines = vec![];
+
+        // the header is text up to an empty line, then the resolution line
+        loop {
+            let end = match data[pos..].iter().position(|&b| b == b'\n') {
+                Some(end) => pos + end,
+                None => return Err("Unexpected end of hdr header".into()),
+            };
+            let line = String::from_utf8_lossy(&data[pos..end]).to_string();
+            pos = end + 1;
+
+            if !lines.is_empty() && line.trim().is_empty() {
+                break;
+            }
+            lines.push(line);
+        }
+
+        if !lines[0].starts_with("#?") {
+            return Err("Not a radiance hdr file".into());
+        }
+        if lines
+            .iter()
+            .any(|l| l.starts_with("FORMAT=") && l != "FORMAT=32-bit_rle_rgbe")
+        {
+            return Err("Only the rgbe hdr format is supported".into());
+        }
+
+        let end = match data[pos..].iter().position(|&b| b == b'\n') {
+            Some(end) => pos + end,
+            None => return Err("Missing hdr resolution".into()),
+        };
+        let resolution = String::from_utf8_lossy(&data[pos..end]).to_string();
+        pos = end + 1;
+
+        let parts: Vec<&str> = resolution.split_whitespace().collect();
+        if parts.len() != 4 || parts[0] != "-Y" || parts[2] != "+X" {
+            return Err(format!("Unsupported hdr orientation {}", resolution));
+        }
+        let height: usize = parts[1].parse().map_err(|_| "Bad hdr height")?;
+        let width: usize = parts[3].parse().map_err(|_| "Bad hdr width")?;
+
+        // a run length encoded scanline packs at most 127 pixels into the
+        // two bytes of a run for each of the 4 channels, so no file holds
+        // more than 16 pixels per byte
+        let size = match width.checked_mul(height) {
+            Some(size) if size > 0 && size / 16 <= data.len() - pos => size,
+            _ => return Err(format!("Bad hdr resolution {}", resolution)),
+        };
+
+        let mut pixels = Vec::with_capacity(size);
+        for _ in 0..height {
+            let scanline = read_scanline(data, &mut pos, width)?;
+            pixels.extend(scanline.iter().map(|&rgbe| rgbe_to_color(rgbe)));
+        }
+
+        EnvironmentMap::new(width, height, pixels)
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn color_in(&self, direction: Vector3) -> Color {
+        let d = direction.normalize();
+        let u = 0.5 + d.x.atan2(-d.z) / (2. * PI);
+        let v = d.y.clamp(-1., 1.).acos() / PI;
+
+        // bilinear filtering, wrapping around horizontally
+        let x = u * self.width as f64 - 0.5;
+        let y =
+            (v * self.height as f64 - 0.5).clamp(0., self.height as f64 - 1.);
+        let x0 = x.floor();
+        let y0 = y.floor();
+        let fx = x - x0;
+        let fy = y - y0;
+
+        let wrap = |x: f64| x.rem_euclid(self.width as f64) as usize;
+        let x1 = wrap(x0 + 1.);
+        let x0 = wrap(x0);
+        let y1 = (y0 as usize + 1).min(self.height - 1);
+        let y0 = y0 as usize;
+
+        let top = self.pixel(x0, y0) * (1. - fx) + self.pixel(x1, y0) * fx;
+        let bottom = self.pixel(x0, y1) * (1. - fx) + self.pixel(x1, y1) * fx;
+        top * (1. - fy) + bottom * fy
+    }
+}
+
+fn rgbe_to_color(rgbe: [u8; 4]) -> Color {
+    if rgbe[3] == 0 {
+        return Color::black();
+    }
+
+    let f = 2_f64.powi(rgbe[3] as i32 - (128 + 8));
+    Color::new(
+        (rgbe[0] as f64 + 0.5) * f,
+        (rgbe[1] as f64 + 0.5) * f,
+        (rgbe[2] as f64 + 0.5) * f,
+    )
+}
+
+fn next_byte(data: &[u8], pos: &mut usize) -> Result<u8, String> {
+    match data.get(*pos) {
+        Some(&b) => {
+            *pos += 1;
+            Ok(b)
+        }
+        None => Err("Unexpected end of hdr data".into()),
+    }
+}
+
+fn read_scanline(
+    data: &[u8],
+    pos: &mut usize,
+    width: usize,
+) -> Result<Vec<[u8; 4]>, String> {
+    let mut scanline = vec![[0_u8; 4]; width];
+
+    let is_rle = (8..0x8000).contains(&width)
+        && data.len() >= *pos + 4
+        && data[*pos] == 2
+        && data[*pos + 1] == 2
+        && ((data[*pos + 2] as usize) << 8 | data[*pos + 3] as usize) == width;
+
+    if !is_rle {
+        for pixel in scanline.iter_mut() {
+            for channel in pixel.iter_mut() {
+                *channel = next_byte(data, pos)?;
+            }
+        }
+        return Ok(scanline);
+    }
+
+    *pos += 4;
+
+    // every channel is stored separately as runs or literal bytes
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let count = next_byte(data, pos)? as usize;
+
+            if count > 128 {
+                let run = count - 128;
+                if x + run > width {
+                    return Err("Bad hdr scanline".into());
+                }
+                let value = next_byte(data, pos)?;
+                for pixel in &mut scanline[x..x + run] {
+                    pixel[channel] = value;
+                }
+                x += run;
+            } else {
+                if count == 0 || x + count > width {
+                    return Err("Bad hdr scanline".into());
+                }
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = next_byte(data, pos)?;
+                }
+                x += count;
+            }
+        }
+    }
+
+    Ok(scanline)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::environment::{Background, EnvironmentMap};
+    use crate::vectors::Vector3;
+
+    #[test]
+    fn test_background_color() {
+        let b = Background::Color(Color::new(0.2, 0.3, 0.4));
+        assert_eq!(
+            b.color_in(Vector3::new(1., 2., 3.)),
+            Color::new(0.2, 0.3, 0.4)
+        );
+    }
+
+    #[test]
+    fn test_background_gradient() {
+        let b = Background::Gradient {
+            zenith: Color::new(0., 0., 1.),
+            horizon: Color::new(1., 1., 1.),
+            ground: Color::new(0., 1., 0.),
+        };
+
+        let up = b.color_in(Vector3::new(0., 1., 0.));
+        let side = b.color_in(Vector3::new(1., 0., 0.));
+        let down = b.color_in(Vector3::new(0., -1., 0.));
+        let between = b.color_in(Vector3::new(0., 1., 1.));
+
+        assert_eq!(up, Color::new(0., 0., 1.));
+        assert_eq!(side, Color::new(1., 1., 1.));
+        assert_eq!(down, Color::new(0., 1., 0.));
+        assert!(between.max_component() <= 1.);
+        assert!(between != up && between != side);
+    }
+
+    fn quadrants() -> EnvironmentMap {
+        // top half is the sky, bottom half the ground, the left half of the
+        // image looks towards -x and the right half towards +x
+        let colors = [
+            [Color::new(0., 0., 1.), Color::new(0., 1., 1.)],
+            [Color::new(0., 1., 0.), Color::new(1., 1., 0.)],
+        ];
+
+        let mut pixels = vec![];
+        for y in 0..8 {
+            for x in 0..8 {
+                pixels.push(colors[y / 4][x / 4]);
+            }
+        }
+        EnvironmentMap::new(8, 8, pixels).unwrap()
+    }
+
+    #[test]
+    fn test_environment_map_lookup() {
+        let map = quadrants();
+
+        let east_up = Vector3::new(1., 0.5, 0.2);
+        let east_down = Vector3::new(1., -0.5, -0.2);
+        let west_up = Vector3::new(-1., 0.5, 0.2);
+        let west_down = Vector3::new(-1., -0.5, -0.2);
+
+        assert_eq!(map.color_in(east_up), Color::new(0., 1., 1.));
+        assert_eq!(map.color_in(east_down), Color::new(1., 1., 0.));
+        assert_eq!(map.color_in(west_up), Color::new(0., 0., 1.));
+        assert_eq!(map.color_in(west_down), Color::new(0., 1., 0.));
+    }
+
+    #[test]
+    fn test_environment_map_wraps() {
+        let map = quadrants();
+
+        // straight behind is the seam where both halves of the image meet
+        let behind = map.color_in(Vector3::new(0., 0.5, 1.));
+        assert_eq!(behind, Color::new(0., 0.5, 1.));
+    }
+
+    #[test]
+    fn test_environment_map_size() {
+        assert!(EnvironmentMap::new(0, 2, vec![]).is_err());
+        assert!(EnvironmentMap::new(2, 2, vec![Color::black(); 3]).is_err());
+    }
+
+    fn header(width: usize, height: usize) -> Vec<u8> {
+        format!(
+            "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n",
+            height, width
+        )
+        .into_bytes()
+    }
+
+    #[test]
+    fn test_hdr_flat() {
+        let mut data = header(2, 1);
+        // 1.0 is 128 with an exponent of 129, the second pixel is black
+        data.extend_from_slice(&[128, 64, 0, 129, 0, 0, 0, 0]);
+
+        let map = EnvironmentMap::from_hdr_bytes(&data).unwrap();
+        assert_eq!(map.width, 2);
+        assert_eq!(map.height, 1);
+        assert_eq!(map.pixel(0, 0), Color::new(1.0039, 0.5039, 0.0039));
+        assert_eq!(map.pixel(1, 0), Color::black());
+    }
+
+    #[test]
+    fn test_hdr_rle() {
+        let mut data = header(8, 1);
+        data.extend_from_slice(&[2, 2, 0, 8]);
+        // red: a run of 8, green: 8 literal bytes, blue: two runs of 4
+        data.extend_from_slice(&[128 + 8, 128]);
+        data.extend_from_slice(&[8, 0, 16, 32, 48, 64, 80, 96, 112]);
+        data.extend_from_slice(&[128 + 4, 0, 128 + 4, 255]);
+        // exponent: run of 8
+        data.extend_from_slice(&[128 + 8, 130]);
+
+        let map = EnvironmentMap::from_hdr_bytes(&data).unwrap();
+        assert_eq!(map.pixel(0, 0), Color::new(2.0078, 0.0078, 0.0078));
+        assert_eq!(map.pixel(7, 0), Color::new(2.0078, 1.7578, 3.9922));
+    }
+
+    #[test]
+    fn test_hdr_invalid() {
+        assert!(EnvironmentMap::from_hdr_bytes(b"P3\n1 1\n255\n").is_err());
+
+        let mut truncated = header(2, 2);
+        truncated.extend_from_slice(&[1, 2, 3]);
+        assert!(EnvironmentMap::from_hdr_bytes(&truncated).is_err());
+
+        // sizes far beyond what the data could hold are turned down before
+        // anything gets allocated for them
+        for (width, height) in [(usize::MAX, 2), (1 << 20, 1 << 20), (0, 9)] {
+            let mut huge = header(width, height);
+            huge.extend_from_slice(&[2, 2, 0, 8]);
+            assert!(EnvironmentMap::from_hdr_bytes(&huge).is_err());
+        }
+    }
+}
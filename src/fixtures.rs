@@ -0,0 +1,61 @@
+use crate::geometries::Sphere;
+use crate::ray::Ray;
+use crate::transformations::{scaling, translation};
+use crate::vectors::{Point3, Vector3};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// random scenes and rays for the tests that check the acceleration
+// structures against brute force. Every test starts from the same seed, so
+// a failure shows up again on the next run
+pub fn rng() -> StdRng {
+    StdRng::seed_from_u64(0x70_61_70_72)
+}
+
+// anywhere in the box between the two corners
+pub fn point_in(rng: &mut StdRng, min: Point3, max: Point3) -> Point3 {
+    let mut between = |a: f64, b: f64| a + rng.gen::<f64>() * (b - a);
+    Point3::new(
+        between(min.x, max.x),
+        between(min.y, max.y),
+        between(min.z, max.z),
+    )
+}
+
+// starts in the box and goes any which way
+pub fn ray_in(rng: &mut StdRng, min: Point3, max: Point3) -> Ray {
+    let origin = point_in(rng, min, max);
+    let direction = Vector3::new(
+        rng.gen::<f64>() - 0.5,
+        rng.gen::<f64>() - 0.5,
+        rng.gen::<f64>() - 0.5,
+    );
+    Ray::new(origin, direction.normalize())
+}
+
+// starts in the box and heads off roughly towards +z
+pub fn ray_forward(rng: &mut StdRng, min: Point3, max: Point3) -> Ray {
+    let origin = point_in(rng, min, max);
+    let direction =
+        Vector3::new(rng.gen::<f64>() - 0.5, rng.gen::<f64>() - 0.5, 1.);
+    Ray::new(origin, direction.normalize())
+}
+
+// centers in the box, radii from the range
+pub fn spheres(
+    rng: &mut StdRng,
+    n: usize,
+    min: Point3,
+    max: Point3,
+    radius: (f64, f64),
+) -> Vec<Sphere> {
+    (0..n)
+        .map(|_| {
+            let c = point_in(rng, min, max);
+            let r = radius.0 + rng.gen::<f64>() * (radius.1 - radius.0);
+            let mut s = Sphere::new();
+            s.set_transform(translation(c.x, c.y, c.z) * scaling(r, r, r));
+            s
+        })
+        .collect()
+}
@@ -1,26 +1,119 @@
-use crate::intersections::Intersection;
+use crate::bounds::BoundingBox;
+use crate::intersections::{Intersection, Intersections};
 use crate::materials::Material;
 use crate::matrix::M;
+use crate::motion::{Decomposed, Motion};
+use crate::packet::{keep_closer, scalar_lanes, Hits, Mask, RayPacket, LANES};
 use crate::ray::Ray;
-use crate::vectors::Tuple;
-use uuid::Uuid;
+use crate::utils::EPSILON;
+use crate::vectors::{Normal3, Point3};
+
+// where a shape sits in the world that holds it, handed out by
+// `World::add`. Shapes that aren't in a world yet have `ShapeId::NONE`, so
+// they can't be mistaken for the world's first object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShapeId(pub u32);
+
+impl ShapeId {
+    pub const NONE: ShapeId = ShapeId(u32::MAX);
+}
+
+impl Default for ShapeId {
+    fn default() -> Self {
+        ShapeId::NONE
+    }
+}
+
+pub trait Shape {
+    fn id(&self) -> ShapeId;
+    fn set_id(&mut self, id: ShapeId);
+    fn transform(&self) -> &M;
+    fn material(&self) -> &Material;
+    fn material_mut(&mut self) -> &mut Material;
+
+    // adds the hits to the buffer, whatever is in there already stays
+    fn intersect_into(&self, ray: Ray, xs: &mut Intersections);
+
+    fn intersects(&self, ray: Ray) -> Vec<Intersection> {
+        let mut xs = Intersections::new();
+        self.intersect_into(ray, &mut xs);
+        xs.into_vec()
+    }
+    // closest hit of every ray of the packet that is in the mask, only
+    // kept where it beats what the lane has already
+    fn intersect_packet(
+        &self,
+        packet: &RayPacket,
+        mask: Mask,
+        hits: &mut Hits,
+    ) {
+        scalar_lanes(self, packet, mask, hits);
+    }
+
+    // the ray's time picks where a moving shape is
+    fn normal_at(&self, p: Point3, time: f64) -> Normal3;
+
+    // normal where the hit landed, shapes made out of many pieces look up
+    // the piece from the hit instead of searching for it
+    fn normal_at_hit(
+        &self,
+        p: Point3,
+        _hit: &Intersection,
+        time: f64,
+    ) -> Normal3 {
+        self.normal_at(p, time)
+    }
+
+    // box around the shape in its own coordinates
+    fn bounds(&self) -> BoundingBox;
+
+    // box around the shape in the coordinates of whatever holds it
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.bounds().transform(self.transform())
+    }
+
+    // what goes into the world's arena when the shape is added, a group
+    // hands over its children instead of itself
+    fn into_parts(self: Box<Self>) -> Vec<Box<dyn Shape>>;
+
+    // made out of other shapes that report hits on themselves, so there's
+    // no surface of its own to shade
+    fn is_aggregate(&self) -> bool {
+        false
+    }
+
+    // applies m after the shape's own transform, groups use it to move
+    // their children along with them
+    fn transform_by(&mut self, m: &M);
+}
 
 pub struct Sphere {
-    pub id: Uuid,
+    pub id: ShapeId,
     transform: M,
     transform_inv: M,
-    transform_inv_t: M,
+    // a moving object is where its motion puts it at the ray's time, the
+    // cached inverses above are its two ends and anything in between is
+    // worked out for every ray
+    motion: Option<Motion>,
+    end_inv: M,
 
     pub material: Material,
 }
 
+impl Default for Sphere {
+    fn default() -> Self {
+        Sphere::new()
+    }
+}
+
 impl Sphere {
     pub fn new() -> Sphere {
         Sphere {
-            id: Uuid::new_v4(),
+            id: ShapeId::NONE,
             transform: M::ident(4),
             transform_inv: M::ident(4),
-            transform_inv_t: M::ident(4),
+            motion: None,
+            end_inv: M::ident(4),
             material: Material::default(),
         }
     }
@@ -28,62 +121,300 @@ impl Sphere {
     pub fn set_transform(&mut self, m: M) {
         self.transform = m.clone();
         self.transform_inv = m.inverse();
-        self.transform_inv_t = m.inverse().transpose();
+        self.motion = None;
+    }
+
+    // moves from the start transform at time 0 to the end one at time 1,
+    // the start transform is the one used by rays without a time. Both go
+    // through the motion's decomposition even at the ends, so any shearing
+    // is dropped the same way all through the shutter
+    pub fn set_motion(&mut self, start: M, end: M) {
+        let motion = Motion::new(&start, &end);
+        self.transform = motion.start.matrix();
+        self.transform_inv = motion.start.inverse_matrix();
+        self.end_inv = motion.end.inverse_matrix();
+        self.motion = Some(motion);
+    }
+
+    pub fn motion(&self) -> Option<&Motion> {
+        self.motion.as_ref()
+    }
+
+    // where the sphere is part way through its motion, the ends have
+    // their inverses cached instead
+    fn between(&self, time: f64) -> Option<Decomposed> {
+        match &self.motion {
+            Some(motion) if time > 0. && time < 1. => Some(motion.at(time)),
+            _ => None,
+        }
     }
 
+    fn inverse_at(&self, time: f64) -> &M {
+        match &self.motion {
+            Some(_) if time >= 1. => &self.end_inv,
+            _ => &self.transform_inv,
+        }
+    }
+
+    // in between the ends the ray is undone part by part, building a
+    // matrix for it would allocate on every ray
     fn transformed_ray(&self, ray: Ray) -> Ray {
-        ray.transform(&self.transform_inv)
+        match self.between(ray.time) {
+            Some(d) => Ray::new(
+                d.inverse_point(ray.origin),
+                d.inverse_vector(ray.direction),
+            )
+            .at_time(ray.time),
+            None => ray.transform(self.inverse_at(ray.time)),
+        }
+    }
+
+    pub fn normal(&self, p: Point3) -> Normal3 {
+        let ob_normal =
+            Normal3::from_vector(&self.transform_inv * p - Point3::origin());
+        ob_normal.transform(&self.transform_inv).normalize()
+    }
+}
+
+impl Shape for Sphere {
+    fn id(&self) -> ShapeId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ShapeId) {
+        self.id = id;
+    }
+
+    fn transform(&self) -> &M {
+        &self.transform
     }
 
-    pub fn intersects(&self, ray_original: Ray) -> Vec<Intersection> {
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersect_into(&self, ray_original: Ray, xs: &mut Intersections) {
         let ray = self.transformed_ray(ray_original);
 
-        let sphere_to_ray = ray.origin - Tuple::point(0., 0., 0.);
+        let sphere_to_ray = ray.origin - Point3::new(0., 0., 0.);
         let a = ray.direction.dot(&ray.direction);
         let b = 2. * ray.direction.dot(&sphere_to_ray);
         let c = sphere_to_ray.dot(&sphere_to_ray) - 1.;
 
         let discriminant = b.powi(2) - 4. * a * c;
         if discriminant < 0. {
-            return vec![];
+            return;
         }
 
-        let t1 = (-b - discriminant.sqrt()) / (2. * a);
-        let t2 = (-b + discriminant.sqrt()) / (2. * a);
-
-        vec![
-            Intersection {
-                object_id: self.id,
-                t: t1,
-            },
-            Intersection {
+        for t in [
+            (-b - discriminant.sqrt()) / (2. * a),
+            (-b + discriminant.sqrt()) / (2. * a),
+        ] {
+            xs.push(Intersection {
                 object_id: self.id,
-                t: t2,
-            },
-        ]
+                t,
+                primitive: 0,
+            });
+        }
+    }
+
+    // all lanes at once, a moving sphere is somewhere else for every lane
+    // so it goes one ray at a time
+    fn intersect_packet(
+        &self,
+        packet: &RayPacket,
+        mask: Mask,
+        hits: &mut Hits,
+    ) {
+        if self.motion.is_some() {
+            return scalar_lanes(self, packet, mask, hits);
+        }
+
+        let local = packet.transform(&self.transform_inv);
+        let (o, d) = (&local.origin, &local.direction);
+        let mut near = [f64::NAN; LANES];
+        let mut far = [f64::NAN; LANES];
+        for l in 0..LANES {
+            let a = d[0][l] * d[0][l] + d[1][l] * d[1][l] + d[2][l] * d[2][l];
+            let b = 2.
+                * (d[0][l] * o[0][l] + d[1][l] * o[1][l] + d[2][l] * o[2][l]);
+            let c =
+                o[0][l] * o[0][l] + o[1][l] * o[1][l] + o[2][l] * o[2][l] - 1.;
+            // a miss gives NaN, which keep_closer never takes
+            let root = (b * b - 4. * a * c).sqrt();
+            near[l] = (-b - root) / (2. * a);
+            far[l] = (-b + root) / (2. * a);
+        }
+
+        for l in (0..LANES).filter(|&l| mask[l]) {
+            let t = if near[l] >= 0. { near[l] } else { far[l] };
+            keep_closer(
+                hits,
+                l,
+                Intersection {
+                    object_id: self.id,
+                    t,
+                    primitive: 0,
+                },
+            );
+        }
+    }
+
+    // normal of a moving sphere where it is at the given time
+    fn normal_at(&self, p: Point3, time: f64) -> Normal3 {
+        if let Some(d) = self.between(time) {
+            let ob_normal =
+                Normal3::from_vector(d.inverse_point(p) - Point3::origin());
+            return d.normal_to_world(ob_normal).normalize();
+        }
+
+        let inverse = self.inverse_at(time);
+        let ob_normal = Normal3::from_vector(inverse * p - Point3::origin());
+        ob_normal.transform(inverse).normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(Point3::new(-1., -1., -1.), Point3::new(1., 1., 1.))
+    }
+
+    // a moving sphere needs a box around its whole path, the path is
+    // followed in steps because a rotation can swing out between the ends
+    fn parent_space_bounds(&self) -> BoundingBox {
+        let motion = match &self.motion {
+            Some(motion) => motion,
+            None => return self.bounds().transform(&self.transform),
+        };
+
+        let steps = 8;
+        (0..=steps).fold(BoundingBox::empty(), |b, i| {
+            let m = motion.at(i as f64 / steps as f64).matrix();
+            b.merge(&self.bounds().transform(&m))
+        })
+    }
+
+    fn into_parts(self: Box<Self>) -> Vec<Box<dyn Shape>> {
+        vec![self]
+    }
+
+    fn transform_by(&mut self, m: &M) {
+        match self.motion {
+            Some(motion) => self.set_motion(
+                m * &motion.start.matrix(),
+                m * &motion.end.matrix(),
+            ),
+            None => self.set_transform(m * &self.transform),
+        }
+    }
+}
+
+// the xz plane, endless in both directions
+pub struct Plane {
+    pub id: ShapeId,
+    transform: M,
+    transform_inv: M,
+
+    pub material: Material,
+}
+
+impl Default for Plane {
+    fn default() -> Self {
+        Plane::new()
+    }
+}
+
+impl Plane {
+    pub fn new() -> Plane {
+        Plane {
+            id: ShapeId::NONE,
+            transform: M::ident(4),
+            transform_inv: M::ident(4),
+            material: Material::default(),
+        }
     }
 
-    pub fn normal(&self, p: Tuple) -> Tuple {
-        let ob_point = &self.transform_inv * p;
-        let ob_normal = ob_point - Tuple::point(0., 0., 0.);
-        let mut world_normal = &self.transform_inv_t * ob_normal;
-        world_normal.w = 0.;
-        world_normal.normalize()
+    pub fn set_transform(&mut self, m: M) {
+        self.transform = m.clone();
+        self.transform_inv = m.inverse();
+    }
+}
+
+impl Shape for Plane {
+    fn id(&self) -> ShapeId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ShapeId) {
+        self.id = id;
+    }
+
+    fn transform(&self) -> &M {
+        &self.transform
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersect_into(&self, ray_original: Ray, xs: &mut Intersections) {
+        let ray = ray_original.transform(&self.transform_inv);
+
+        // parallel rays never hit it, coplanar ones are ignored too
+        if ray.direction.y.abs() < EPSILON {
+            return;
+        }
+
+        xs.push(Intersection {
+            object_id: self.id,
+            t: -ray.origin.y / ray.direction.y,
+            primitive: 0,
+        });
+    }
+
+    fn normal_at(&self, _p: Point3, _time: f64) -> Normal3 {
+        Normal3::new(0., 1., 0.)
+            .transform(&self.transform_inv)
+            .normalize()
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            Point3::new(f64::NEG_INFINITY, 0., f64::NEG_INFINITY),
+            Point3::new(f64::INFINITY, 0., f64::INFINITY),
+        )
+    }
+
+    fn into_parts(self: Box<Self>) -> Vec<Box<dyn Shape>> {
+        vec![self]
+    }
+
+    fn transform_by(&mut self, m: &M) {
+        self.set_transform(m * &self.transform);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::geometries::Sphere;
+    use crate::geometries::{Plane, Shape, Sphere};
+    use crate::intersections::Intersections;
     use crate::ray::Ray;
-    use crate::transformations::translation;
+    use crate::transformations::{
+        rotation_x, rotation_z, scaling, shearing, translation,
+    };
     use crate::utils::float_compare;
-    use crate::vectors::Tuple;
-    use std::f64::consts::FRAC_1_SQRT_2;
+    use crate::vectors::{Normal3, Point3, Vector3};
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
     #[test]
     fn test_intersection_1() {
-        let r = Ray::new(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
 
         let sph = Sphere::new();
         let ints = sph.intersects(r);
@@ -96,7 +427,7 @@ mod tests {
 
     #[test]
     fn test_intersection_2() {
-        let r = Ray::new(Tuple::point(0., 1., -5.), Tuple::vector(0., 0., 1.));
+        let r = Ray::new(Point3::new(0., 1., -5.), Vector3::new(0., 0., 1.));
 
         let sph = Sphere::new();
         let ints = sph.intersects(r);
@@ -109,7 +440,7 @@ mod tests {
 
     #[test]
     fn test_intersection_miss() {
-        let r = Ray::new(Tuple::point(0., 2., -5.), Tuple::vector(0., 0., 1.));
+        let r = Ray::new(Point3::new(0., 2., -5.), Vector3::new(0., 0., 1.));
 
         let sph = Sphere::new();
         let ints = sph.intersects(r);
@@ -119,7 +450,7 @@ mod tests {
 
     #[test]
     fn test_intersection_inside() {
-        let r = Ray::new(Tuple::point(0., 0., 0.), Tuple::vector(0., 0., 1.));
+        let r = Ray::new(Point3::new(0., 0., 0.), Vector3::new(0., 0., 1.));
 
         let sph = Sphere::new();
         let ints = sph.intersects(r);
@@ -130,9 +461,23 @@ mod tests {
         assert_eq!(ints[1].t, 1.);
     }
 
+    #[test]
+    fn test_intersect_into_appends() {
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        let mut xs = Intersections::new();
+
+        Sphere::new().intersect_into(r, &mut xs);
+        let mut p = Plane::new();
+        p.set_transform(rotation_x(PI / 2.));
+        p.intersect_into(r, &mut xs);
+
+        let ts: Vec<f64> = xs.iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4., 6., 5.]);
+    }
+
     #[test]
     fn test_intersection_behind() {
-        let r = Ray::new(Tuple::point(0., 0., 5.), Tuple::vector(0., 0., 1.));
+        let r = Ray::new(Point3::new(0., 0., 5.), Vector3::new(0., 0., 1.));
 
         let sph = Sphere::new();
         let ints = sph.intersects(r);
@@ -145,11 +490,11 @@ mod tests {
 
     #[test]
     fn test_point_calc() {
-        let r = Ray::new(Tuple::point(1., 1., 1.), Tuple::vector(0., 0., 1.));
+        let r = Ray::new(Point3::new(1., 1., 1.), Vector3::new(0., 0., 1.));
 
-        let sphere_to_ray = r.origin - Tuple::point(0., 0., 0.);
+        let sphere_to_ray = r.origin - Point3::new(0., 0., 0.);
 
-        assert_eq!(sphere_to_ray, Tuple::vector(1., 1., 1.))
+        assert_eq!(sphere_to_ray, Vector3::new(1., 1., 1.))
     }
 
     #[test]
@@ -157,24 +502,24 @@ mod tests {
         let sph = Sphere::new();
 
         assert_eq!(
-            sph.normal(Tuple::point(1., 0., 0.)),
-            Tuple::vector(1., 0., 0.)
+            sph.normal(Point3::new(1., 0., 0.)),
+            Normal3::new(1., 0., 0.)
         );
 
         assert_eq!(
-            sph.normal(Tuple::point(0., 1., 0.)),
-            Tuple::vector(0., 1., 0.)
+            sph.normal(Point3::new(0., 1., 0.)),
+            Normal3::new(0., 1., 0.)
         );
 
         assert_eq!(
-            sph.normal(Tuple::point(0., 0., 1.)),
-            Tuple::vector(0., 0., 1.)
+            sph.normal(Point3::new(0., 0., 1.)),
+            Normal3::new(0., 0., 1.)
         );
 
         // nonaxical point:
         let t: f64 = (3_f64).sqrt() / 3.;
 
-        assert_eq!(sph.normal(Tuple::point(t, t, t)), Tuple::vector(t, t, t));
+        assert_eq!(sph.normal(Point3::new(t, t, t)), Normal3::new(t, t, t));
     }
 
     #[test]
@@ -184,8 +529,147 @@ mod tests {
         sph.set_transform(translation(0., 1., 0.));
 
         assert_eq!(
-            sph.normal(Tuple::point(0., 1. + FRAC_1_SQRT_2, -FRAC_1_SQRT_2)),
-            Tuple::vector(0., FRAC_1_SQRT_2, -FRAC_1_SQRT_2)
+            sph.normal(Point3::new(0., 1. + FRAC_1_SQRT_2, -FRAC_1_SQRT_2)),
+            Normal3::new(0., FRAC_1_SQRT_2, -FRAC_1_SQRT_2)
+        );
+    }
+
+    #[test]
+    fn test_moving_sphere() {
+        let mut sph = Sphere::new();
+        sph.set_motion(translation(0., 0., 0.), translation(0., 2., 0.));
+        let r = Ray::new(Point3::new(0., 2., -5.), Vector3::new(0., 0., 1.));
+
+        // at the start the ray passes high over the top
+        assert_eq!(sph.intersects(r).len(), 0);
+
+        let ints = sph.intersects(r.at_time(1.));
+        assert_eq!(ints[0].t, 4.);
+        assert_eq!(ints[1].t, 6.);
+
+        // halfway up the ray just touches it
+        let ints = sph.intersects(r.at_time(0.5));
+        assert_eq!(ints[0].t, 5.);
+        assert_eq!(ints[1].t, 5.);
+    }
+
+    #[test]
+    fn test_moving_sphere_normal() {
+        let mut sph = Sphere::new();
+        sph.set_motion(scaling(1., 1., 1.), translation(3., 0., 0.));
+
+        assert_eq!(
+            sph.normal_at(Point3::new(4., 0., 0.), 1.),
+            Normal3::new(1., 0., 0.)
         );
+        assert_eq!(
+            sph.normal_at(Point3::new(1., 0., 0.), 0.),
+            Normal3::new(1., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn test_moving_sheared_sphere() {
+        let mut sph = Sphere::new();
+        let start = shearing(1., 0., 0., 0., 0., 0.);
+        sph.set_motion(start.clone(), translation(2., 0., 0.) * start);
+
+        // the shear is gone right from the start instead of snapping away
+        // once the shutter opens
+        let p = Point3::new(0.6, 0.8, 0.);
+        let r = Ray::new(Point3::new(0., 0.5, -5.), Vector3::new(0., 0., 1.));
+        for (a, b) in [(0., 1e-9), (1. - 1e-9, 1.)] {
+            let (xs, ys) =
+                (sph.intersects(r.at_time(a)), sph.intersects(r.at_time(b)));
+            assert_eq!(xs.len(), ys.len());
+            for (x, y) in xs.iter().zip(ys.iter()) {
+                assert!(float_compare(x.t, y.t));
+            }
+        }
+        assert_eq!(sph.normal_at(p, 0.), sph.normal_at(p, 1e-9));
+    }
+
+    #[test]
+    fn test_set_transform_stops_motion() {
+        let mut sph = Sphere::new();
+        sph.set_motion(translation(0., 0., 0.), translation(0., 2., 0.));
+        assert!(sph.motion().is_some());
+
+        sph.set_transform(translation(1., 0., 0.));
+        assert!(sph.motion().is_none());
+    }
+
+    #[test]
+    fn test_sphere_bounds() {
+        let mut sph = Sphere::new();
+        let b = sph.bounds();
+        assert_eq!(b.min, Point3::new(-1., -1., -1.));
+        assert_eq!(b.max, Point3::new(1., 1., 1.));
+
+        sph.set_transform(translation(1., -3., 5.) * scaling(0.5, 2., 4.));
+        let b = sph.parent_space_bounds();
+        assert_eq!(b.min, Point3::new(0.5, -5., 1.));
+        assert_eq!(b.max, Point3::new(1.5, -1., 9.));
+    }
+
+    #[test]
+    fn test_moving_sphere_bounds() {
+        let mut sph = Sphere::new();
+        sph.set_motion(translation(0., 0., 0.), translation(4., 0., 0.));
+
+        let b = sph.parent_space_bounds();
+        assert_eq!(b.min, Point3::new(-1., -1., -1.));
+        assert_eq!(b.max, Point3::new(5., 1., 1.));
+    }
+
+    #[test]
+    fn test_plane_normal() {
+        let p = Plane::new();
+        for point in [
+            Point3::new(0., 0., 0.),
+            Point3::new(10., 0., -10.),
+            Point3::new(-5., 0., 150.),
+        ] {
+            assert_eq!(p.normal_at(point, 0.), Normal3::new(0., 1., 0.));
+        }
+    }
+
+    #[test]
+    fn test_plane_intersections() {
+        let p = Plane::new();
+
+        let parallel =
+            Ray::new(Point3::new(0., 10., 0.), Vector3::new(0., 0., 1.));
+        assert!(p.intersects(parallel).is_empty());
+        let coplanar =
+            Ray::new(Point3::new(0., 0., 0.), Vector3::new(0., 0., 1.));
+        assert!(p.intersects(coplanar).is_empty());
+
+        let above =
+            Ray::new(Point3::new(0., 1., 0.), Vector3::new(0., -1., 0.));
+        let ints = p.intersects(above);
+        assert_eq!(ints.len(), 1);
+        assert_eq!(ints[0].t, 1.);
+        assert_eq!(ints[0].object_id, p.id);
+
+        let below =
+            Ray::new(Point3::new(0., -1., 0.), Vector3::new(0., 1., 0.));
+        assert_eq!(p.intersects(below)[0].t, 1.);
+    }
+
+    #[test]
+    fn test_plane_bounds() {
+        let mut p = Plane::new();
+        let b = p.bounds();
+        assert!(b.is_infinite());
+        assert_eq!(b.min.y, 0.);
+        assert_eq!(b.max.y, 0.);
+
+        // tilted it isn't flat along any axis any more
+        p.set_transform(rotation_z(0.3));
+        let b = p.parent_space_bounds();
+        assert_eq!(b.min.y, f64::NEG_INFINITY);
+        assert_eq!(b.max.x, f64::INFINITY);
+        assert_eq!(b.min.z, f64::NEG_INFINITY);
     }
 }
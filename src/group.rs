@@ -0,0 +1,297 @@
+use crate::bounds::BoundingBox;
+use crate::bvh::Bvh;
+use crate::geometries::{Shape, ShapeId};
+use crate::intersections::Intersections;
+use crate::materials::Material;
+use crate::matrix::M;
+use crate::ray::Ray;
+use crate::vectors::{Normal3, Point3};
+use std::sync::OnceLock;
+
+// a bunch of shapes moved around together. The group's transform is baked
+// into the children as they are added, so they never need to know about
+// their parent and hits are reported on the children themselves. Until the
+// world takes the children over their ids are their place in the group
+pub struct Group {
+    pub id: ShapeId,
+    transform: M,
+    transform_inv: M,
+    children: Vec<Box<dyn Shape>>,
+    // built by the first ray after the children last changed, so adding
+    // them one by one doesn't rebuild it every time
+    bvh: OnceLock<Bvh>,
+
+    // only there for the trait, hits take the child's material
+    material: Material,
+}
+
+impl Default for Group {
+    fn default() -> Self {
+        Group::new()
+    }
+}
+
+impl Group {
+    pub fn new() -> Group {
+        Group {
+            id: ShapeId::NONE,
+            transform: M::ident(4),
+            transform_inv: M::ident(4),
+            children: vec![],
+            bvh: OnceLock::new(),
+            material: Material::default(),
+        }
+    }
+
+    pub fn add_child(&mut self, mut child: Box<dyn Shape>) {
+        child.transform_by(&self.transform);
+        child.set_id(ShapeId(self.children.len() as u32));
+        self.children.push(child);
+        self.bvh.take();
+    }
+
+    pub fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+
+    // replaces the transform, the children first get the old one undone
+    pub fn set_transform(&mut self, m: M) {
+        let change = &m * &self.transform_inv;
+        for child in &mut self.children {
+            child.transform_by(&change);
+        }
+        self.transform_inv = m.inverse();
+        self.transform = m;
+        self.bvh.take();
+    }
+
+    fn bvh(&self) -> &Bvh {
+        self.bvh.get_or_init(|| {
+            let bounds: Vec<BoundingBox> = self
+                .children
+                .iter()
+                .map(|c| c.parent_space_bounds())
+                .collect();
+            Bvh::build(&bounds)
+        })
+    }
+}
+
+impl Shape for Group {
+    fn id(&self) -> ShapeId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ShapeId) {
+        self.id = id;
+    }
+
+    fn transform(&self) -> &M {
+        &self.transform
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    // the children already live in the group's parent space, the ray goes
+    // to them as it is
+    fn intersect_into(&self, ray: Ray, xs: &mut Intersections) {
+        self.bvh()
+            .candidates(ray, |i| self.children[i].intersect_into(ray, xs));
+    }
+
+    // hits always belong to one of the children
+    fn normal_at(&self, _p: Point3, _time: f64) -> Normal3 {
+        panic!("a group has no surface of its own")
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.parent_space_bounds().transform(&self.transform_inv)
+    }
+
+    fn parent_space_bounds(&self) -> BoundingBox {
+        self.children.iter().fold(BoundingBox::empty(), |b, c| {
+            b.merge(&c.parent_space_bounds())
+        })
+    }
+
+    // the children are already where the group puts them, the group
+    // itself has nothing left to add
+    fn into_parts(self: Box<Self>) -> Vec<Box<dyn Shape>> {
+        self.children
+            .into_iter()
+            .flat_map(|c| c.into_parts())
+            .collect()
+    }
+
+    fn is_aggregate(&self) -> bool {
+        true
+    }
+
+    fn transform_by(&mut self, m: &M) {
+        for child in &mut self.children {
+            child.transform_by(m);
+        }
+        self.transform = m * &self.transform;
+        self.transform_inv = self.transform.inverse();
+        self.bvh.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::geometries::{Plane, Shape, ShapeId, Sphere};
+    use crate::group::Group;
+    use crate::ray::Ray;
+    use crate::transformations::{rotation_y, scaling, translation};
+    use crate::vectors::{Normal3, Point3, Vector3};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_empty_group() {
+        let g = Group::new();
+        let r = Ray::new(Point3::new(0., 0., 0.), Vector3::new(0., 0., 1.));
+        assert!(g.intersects(r).is_empty());
+        assert!(g.bounds().is_empty());
+    }
+
+    #[test]
+    fn test_group_intersections() {
+        let mut g = Group::new();
+        let s1 = Sphere::new();
+        let mut s2 = Sphere::new();
+        s2.set_transform(translation(0., 0., -3.));
+        let mut s3 = Sphere::new();
+        s3.set_transform(translation(5., 0., 0.));
+        g.add_child(Box::new(s1));
+        g.add_child(Box::new(s2));
+        g.add_child(Box::new(s3));
+
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        let mut ints = g.intersects(r);
+        ints.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+
+        // the children are numbered in the order they were added
+        assert_eq!(ints.len(), 4);
+        assert_eq!(ints[0].object_id, ShapeId(1));
+        assert_eq!(ints[1].object_id, ShapeId(1));
+        assert_eq!(ints[2].object_id, ShapeId(0));
+        assert_eq!(ints[3].object_id, ShapeId(0));
+    }
+
+    #[test]
+    fn test_children_added_after_a_ray() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        assert_eq!(g.intersects(r).len(), 2);
+
+        // the bvh the first ray built doesn't know about the new child
+        let mut s = Sphere::new();
+        s.set_transform(translation(0., 0., 3.));
+        g.add_child(Box::new(s));
+        assert_eq!(g.intersects(r).len(), 4);
+
+        g.set_transform(translation(10., 0., 0.));
+        assert!(g.intersects(r).is_empty());
+    }
+
+    #[test]
+    fn test_transformed_group() {
+        let mut g = Group::new();
+        g.set_transform(scaling(2., 2., 2.));
+        let mut s = Sphere::new();
+        s.set_transform(translation(5., 0., 0.));
+        g.add_child(Box::new(s));
+
+        let r = Ray::new(Point3::new(10., 0., -10.), Vector3::new(0., 0., 1.));
+        assert_eq!(g.intersects(r).len(), 2);
+
+        // the child's normal comes out in world space
+        let child = &g.children()[0];
+        assert_eq!(
+            child.normal_at(Point3::new(10., 2., 0.), 0.),
+            Normal3::new(0., 1., 0.)
+        );
+    }
+
+    #[test]
+    fn test_set_transform_moves_children() {
+        let mut g = Group::new();
+        g.set_transform(translation(0., 5., 0.));
+        g.add_child(Box::new(Sphere::new()));
+
+        // replacing the transform undoes the old one first
+        g.set_transform(rotation_y(PI / 2.) * translation(3., 0., 0.));
+        let b = g.parent_space_bounds();
+        assert_eq!(b.min, Point3::new(-1., -1., -4.));
+        assert_eq!(b.max, Point3::new(1., 1., -2.));
+        assert_eq!(g.bounds().min, Point3::new(-1., -1., -1.));
+    }
+
+    #[test]
+    fn test_nested_groups() {
+        let mut inner = Group::new();
+        inner.add_child(Box::new(Sphere::new()));
+        inner.set_transform(translation(0., 0., 3.));
+
+        let mut outer = Group::new();
+        outer.add_child(Box::new(Plane::new()));
+        outer.add_child(Box::new(inner));
+        outer.set_transform(translation(1., 0., 0.));
+
+        assert!(outer.parent_space_bounds().is_infinite());
+
+        let r = Ray::new(Point3::new(1., 5., 3.), Vector3::new(0., -1., 0.));
+        let mut ints = outer.intersects(r);
+        ints.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+        // the plane cuts the sphere in half
+        let ts: Vec<f64> = ints.iter().map(|i| i.t).collect();
+        assert_eq!(ts, vec![4., 5., 6.]);
+
+        // taken apart it's just the plane and the sphere, already moved
+        let parts = Box::new(outer).into_parts();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(
+            parts[1].normal_at(Point3::new(1., 1., 3.), 0.),
+            Normal3::new(0., 1., 0.)
+        );
+    }
+
+    #[test]
+    fn test_group_matches_brute_force() {
+        let mut rng = fixtures::rng();
+        let mut g = Group::new();
+        let (min, max) =
+            (Point3::new(-10., -10., -10.), Point3::new(10., 10., 10.));
+        for s in fixtures::spheres(&mut rng, 200, min, max, (0.1, 0.6)) {
+            g.add_child(Box::new(s));
+        }
+        let children = g.children();
+
+        for _ in 0..200 {
+            let r = fixtures::ray_forward(
+                &mut rng,
+                Point3::new(-10., -10., -20.),
+                Point3::new(10., 10., -20.),
+            );
+
+            let mut expected: Vec<f64> = children
+                .iter()
+                .flat_map(|c| c.intersects(r))
+                .map(|i| i.t)
+                .collect();
+            let mut found: Vec<f64> =
+                g.intersects(r).iter().map(|i| i.t).collect();
+            expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            found.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            assert_eq!(found, expected);
+        }
+    }
+}
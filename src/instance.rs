@@ -0,0 +1,234 @@
+use crate::bounds::BoundingBox;
+use crate::geometries::{Shape, ShapeId};
+use crate::intersections::{Intersection, Intersections};
+use crate::materials::Material;
+use crate::matrix::M;
+use crate::ray::Ray;
+use crate::vectors::{Normal3, Point3};
+use std::sync::Arc;
+
+// one more copy of a shared shape somewhere else in the scene, the shape
+// itself is stored only once no matter how many instances point at it.
+// Hits on the shape are reported as hits on the instance, so the shared
+// shape has to be a single surface and groups are turned down
+pub struct Instance {
+    pub id: ShapeId,
+    shape: Arc<dyn Shape>,
+    transform: M,
+    transform_inv: M,
+
+    // none means the shared shape's own material is used
+    pub material: Option<Material>,
+}
+
+impl Instance {
+    pub fn new(shape: Arc<dyn Shape>) -> Result<Instance, String> {
+        if shape.is_aggregate() {
+            return Err("Only a single surface can be instanced".into());
+        }
+
+        Ok(Instance {
+            id: ShapeId::NONE,
+            shape,
+            transform: M::ident(4),
+            transform_inv: M::ident(4),
+            material: None,
+        })
+    }
+
+    pub fn set_transform(&mut self, m: M) {
+        self.transform = m.clone();
+        self.transform_inv = m.inverse();
+    }
+
+    pub fn shape(&self) -> &Arc<dyn Shape> {
+        &self.shape
+    }
+
+    fn to_world(&self, local_normal: Normal3) -> Normal3 {
+        local_normal.transform(&self.transform_inv).normalize()
+    }
+}
+
+impl Shape for Instance {
+    fn id(&self) -> ShapeId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ShapeId) {
+        self.id = id;
+    }
+
+    fn transform(&self) -> &M {
+        &self.transform
+    }
+
+    fn material(&self) -> &Material {
+        self.material
+            .as_ref()
+            .unwrap_or_else(|| self.shape.material())
+    }
+
+    // changing the material only changes this instance, the shared one
+    // stays as it is
+    fn material_mut(&mut self) -> &mut Material {
+        let shared = *self.shape.material();
+        self.material.get_or_insert(shared)
+    }
+
+    fn intersect_into(&self, ray: Ray, xs: &mut Intersections) {
+        let start = xs.len();
+        self.shape
+            .intersect_into(ray.transform(&self.transform_inv), xs);
+        for i in &mut xs[start..] {
+            i.object_id = self.id;
+        }
+    }
+
+    fn normal_at(&self, p: Point3, time: f64) -> Normal3 {
+        let local_normal = self.shape.normal_at(&self.transform_inv * p, time);
+        self.to_world(local_normal)
+    }
+
+    // the hit's primitive is the shared shape's own, it can go along as is
+    fn normal_at_hit(
+        &self,
+        p: Point3,
+        hit: &Intersection,
+        time: f64,
+    ) -> Normal3 {
+        let local_normal =
+            self.shape.normal_at_hit(&self.transform_inv * p, hit, time);
+        self.to_world(local_normal)
+    }
+
+    // the shared shape's box already includes its own transform
+    fn bounds(&self) -> BoundingBox {
+        self.shape.parent_space_bounds()
+    }
+
+    fn into_parts(self: Box<Self>) -> Vec<Box<dyn Shape>> {
+        vec![self]
+    }
+
+    fn transform_by(&mut self, m: &M) {
+        self.set_transform(m * &self.transform);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::geometries::{Shape, Sphere};
+    use crate::group::Group;
+    use crate::instance::Instance;
+    use crate::ray::Ray;
+    use crate::transformations::{scaling, translation};
+    use crate::vectors::{Normal3, Point3, Vector3};
+    use crate::world::World;
+    use std::f64::consts::FRAC_1_SQRT_2;
+    use std::sync::Arc;
+
+    fn shared_sphere() -> Arc<dyn Shape> {
+        let mut s = Sphere::new();
+        s.material.color = Color::new(0.2, 0.8, 0.2);
+        s.set_transform(scaling(2., 2., 2.));
+        Arc::new(s)
+    }
+
+    #[test]
+    fn test_instance_intersections() {
+        let shared = shared_sphere();
+        let mut inst = Instance::new(shared.clone()).unwrap();
+        inst.set_transform(translation(5., 0., 0.));
+
+        let r = Ray::new(Point3::new(5., 0., -5.), Vector3::new(0., 0., 1.));
+        let ints = inst.intersects(r);
+        assert_eq!(ints.len(), 2);
+        assert_eq!(ints[0].t, 3.);
+        assert_eq!(ints[1].t, 7.);
+        // the hit belongs to the instance, not the shared sphere
+        assert_eq!(ints[0].object_id, inst.id);
+
+        let miss =
+            Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        assert!(inst.intersects(miss).is_empty());
+    }
+
+    #[test]
+    fn test_group_cant_be_instanced() {
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        let shared: Box<dyn Shape> = Box::new(g);
+
+        assert!(Instance::new(Arc::from(shared)).is_err());
+    }
+
+    #[test]
+    fn test_instance_normal() {
+        let mut inst = Instance::new(shared_sphere()).unwrap();
+        inst.set_transform(translation(0., 1., 0.) * scaling(1., 0.5, 1.));
+
+        assert_eq!(
+            inst.normal_at(Point3::new(0., 2., 0.), 0.),
+            Normal3::new(0., 1., 0.)
+        );
+        let n = inst.normal_at(
+            Point3::new(2. * FRAC_1_SQRT_2, 1., -2. * FRAC_1_SQRT_2),
+            0.,
+        );
+        assert_eq!(n, Normal3::new(FRAC_1_SQRT_2, 0., -FRAC_1_SQRT_2));
+    }
+
+    #[test]
+    fn test_material_override() {
+        let shared = shared_sphere();
+        let plain = Instance::new(shared.clone()).unwrap();
+        let mut red = Instance::new(shared.clone()).unwrap();
+        red.material_mut().color = Color::new(1., 0., 0.);
+
+        assert_eq!(plain.material().color, Color::new(0.2, 0.8, 0.2));
+        assert_eq!(red.material().color, Color::new(1., 0., 0.));
+        // everything else comes from the shared material
+        assert_eq!(red.material().diffuse, shared.material().diffuse);
+        assert_eq!(shared.material().color, Color::new(0.2, 0.8, 0.2));
+    }
+
+    #[test]
+    fn test_instance_bounds() {
+        let mut inst = Instance::new(shared_sphere()).unwrap();
+        inst.set_transform(translation(10., 0., 0.));
+
+        let b = inst.parent_space_bounds();
+        assert_eq!(b.min, Point3::new(8., -2., -2.));
+        assert_eq!(b.max, Point3::new(12., 2., 2.));
+    }
+
+    #[test]
+    fn test_forest_shares_one_shape() {
+        let shared = shared_sphere();
+        let mut w = World::new();
+        for i in 0..1000 {
+            let mut inst = Instance::new(shared.clone()).unwrap();
+            inst.set_transform(translation(
+                (i % 100) as f64 * 5.,
+                0.,
+                (i / 100) as f64 * 5.,
+            ));
+            w.add(inst);
+        }
+        w.build_bvh();
+        assert_eq!(Arc::strong_count(&shared), 1001);
+
+        let r =
+            Ray::new(Point3::new(25., 10., 25.), Vector3::new(0., -1., 0.));
+        let ints = w.intersect(r);
+        assert_eq!(ints.len(), 2);
+        assert_eq!(ints[0].t, 8.);
+        let hit = w.object(ints[0].object_id).unwrap();
+        assert_eq!(
+            hit.normal_at(Point3::new(25., 2., 25.), 0.),
+            Normal3::new(0., 1., 0.)
+        );
+    }
+}
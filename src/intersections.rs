@@ -1,44 +1,240 @@
-use uuid::Uuid;
+use crate::geometries::{Shape, ShapeId};
+use crate::ray::Ray;
+use crate::utils::EPSILON;
+use crate::vectors::{Normal3, Point3, Vector3};
+use std::ops::{Deref, DerefMut};
 
 #[derive(Debug, Clone, Copy)]
 pub struct Intersection {
-    pub object_id: Uuid,
+    pub object_id: ShapeId,
     pub t: f64,
+    // which piece of the object got hit, like a triangle of a mesh. Shapes
+    // that are a single surface leave it at 0
+    pub primitive: usize,
 }
 
-pub fn hit(ints: Vec<Intersection>) -> Option<Intersection> {
-    let mut ints_filtered: Vec<Intersection> =
-        ints.iter().cloned().filter(|&i| i.t >= 0.).collect();
+// the closest intersection in front of the ray, without sorting. NaN
+// distances never win a comparison so they are simply passed over
+pub fn hit(ints: &[Intersection]) -> Option<Intersection> {
+    let mut closest: Option<Intersection> = None;
+    for &i in ints {
+        if i.t >= 0. && closest.is_none_or(|c| i.t < c.t) {
+            closest = Some(i);
+        }
+    }
+    closest
+}
+
+// hits of a ray collected by the shapes it went through. Clearing keeps
+// the memory around, so one buffer can serve every ray a thread traces
+#[derive(Debug, Clone, Default)]
+pub struct Intersections {
+    ints: Vec<Intersection>,
+}
+
+impl Intersections {
+    pub fn new() -> Intersections {
+        Intersections { ints: vec![] }
+    }
+
+    pub fn push(&mut self, i: Intersection) {
+        self.ints.push(i);
+    }
+
+    pub fn clear(&mut self) {
+        self.ints.clear();
+    }
+
+    pub fn hit(&self) -> Option<Intersection> {
+        hit(&self.ints)
+    }
+
+    // NaN distances end up at the back
+    pub fn sort(&mut self) {
+        self.ints.sort_by(|a, b| a.t.total_cmp(&b.t));
+    }
+
+    pub fn into_vec(self) -> Vec<Intersection> {
+        self.ints
+    }
+}
+
+impl Deref for Intersections {
+    type Target = [Intersection];
+
+    fn deref(&self) -> &[Intersection] {
+        &self.ints
+    }
+}
+
+impl DerefMut for Intersections {
+    fn deref_mut(&mut self) -> &mut [Intersection] {
+        &mut self.ints
+    }
+}
+
+pub struct Computations<'a> {
+    pub t: f64,
+    pub object: &'a dyn Shape,
+    pub point: Point3,
+    // nudged slightly above the surface so it doesn't shadow itself
+    pub over_point: Point3,
+    pub eyev: Vector3,
+    pub normalv: Normal3,
+    pub inside: bool,
+    // rays leaving the hit have to be sent at the same moment
+    pub time: f64,
+}
+
+pub fn prepare_computations(
+    i: Intersection,
+    ray: Ray,
+    object: &dyn Shape,
+) -> Computations<'_> {
+    let point = ray.position(i.t);
+    let eyev = -ray.direction;
+    let mut normalv = object.normal_at_hit(point, &i, ray.time);
+
+    let inside = normalv.dot(&eyev) < 0.;
+    if inside {
+        normalv = -normalv;
+    }
+    let over_point = point + normalv * EPSILON;
 
-    ints_filtered.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
-    ints_filtered.first().copied()
+    Computations {
+        t: i.t,
+        object,
+        point,
+        over_point,
+        eyev,
+        normalv,
+        inside,
+        time: ray.time,
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::intersections::{hit, Intersection};
-    use crate::utils::float_compare;
-    use uuid::Uuid;
+    use crate::geometries::{ShapeId, Sphere};
+    use crate::intersections::{
+        hit, prepare_computations, Intersection, Intersections,
+    };
+    use crate::ray::Ray;
+    use crate::transformations::translation;
+    use crate::utils::{float_compare, EPSILON};
+    use crate::vectors::{Normal3, Point3, Vector3};
 
     #[test]
     fn test_hit_filter() {
-        let uid = Uuid::new_v4();
+        let id = ShapeId(0);
         let before = vec![
             Intersection {
-                object_id: uid,
+                object_id: id,
                 t: 5.0,
+                primitive: 0,
             },
             Intersection {
-                object_id: uid,
+                object_id: id,
                 t: 2.0,
+                primitive: 0,
             },
             Intersection {
-                object_id: uid,
+                object_id: id,
                 t: -1.0,
+                primitive: 0,
             },
         ];
 
-        let r = hit(before).unwrap();
+        let r = hit(&before).unwrap();
         assert!(float_compare(r.t, 2.0));
     }
+
+    #[test]
+    fn test_hit_skips_nan() {
+        let at = |t: f64| Intersection {
+            object_id: ShapeId(0),
+            t,
+            primitive: 0,
+        };
+
+        assert_eq!(hit(&[at(f64::NAN), at(3.), at(1.)]).unwrap().t, 1.);
+        assert_eq!(hit(&[at(1.), at(f64::NAN)]).unwrap().t, 1.);
+        assert!(hit(&[at(f64::NAN), at(-2.)]).is_none());
+        assert!(hit(&[]).is_none());
+    }
+
+    #[test]
+    fn test_intersections_buffer() {
+        let mut xs = Intersections::new();
+        for t in [4., f64::NAN, -1., 2.] {
+            xs.push(Intersection {
+                object_id: ShapeId(0),
+                t,
+                primitive: 0,
+            });
+        }
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs.hit().unwrap().t, 2.);
+
+        xs.sort();
+        assert_eq!(xs[0].t, -1.);
+        assert_eq!(xs[2].t, 4.);
+        assert!(xs[3].t.is_nan());
+
+        // emptied but ready for the next ray
+        xs.clear();
+        assert!(xs.is_empty());
+        assert!(xs.hit().is_none());
+    }
+
+    #[test]
+    fn test_prepare_computations() {
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        let shape = Sphere::new();
+        let i = Intersection {
+            object_id: shape.id,
+            t: 4.,
+            primitive: 0,
+        };
+
+        let comps = prepare_computations(i, r, &shape);
+        assert!(float_compare(comps.t, 4.));
+        assert_eq!(comps.point, Point3::new(0., 0., -1.));
+        assert_eq!(comps.eyev, Vector3::new(0., 0., -1.));
+        assert_eq!(comps.normalv, Normal3::new(0., 0., -1.));
+        assert!(!comps.inside);
+    }
+
+    #[test]
+    fn test_prepare_computations_inside() {
+        let r = Ray::new(Point3::new(0., 0., 0.), Vector3::new(0., 0., 1.));
+        let shape = Sphere::new();
+        let i = Intersection {
+            object_id: shape.id,
+            t: 1.,
+            primitive: 0,
+        };
+
+        let comps = prepare_computations(i, r, &shape);
+        assert_eq!(comps.point, Point3::new(0., 0., 1.));
+        assert_eq!(comps.eyev, Vector3::new(0., 0., -1.));
+        assert_eq!(comps.normalv, Normal3::new(0., 0., -1.));
+        assert!(comps.inside);
+    }
+
+    #[test]
+    fn test_over_point() {
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        let mut shape = Sphere::new();
+        shape.set_transform(translation(0., 0., 1.));
+        let i = Intersection {
+            object_id: shape.id,
+            t: 5.,
+            primitive: 0,
+        };
+
+        let comps = prepare_computations(i, r, &shape);
+        assert!(comps.over_point.z < -EPSILON / 2.);
+        assert!(comps.point.z > comps.over_point.z);
+    }
 }
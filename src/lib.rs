@@ -0,0 +1,32 @@
+pub mod ambient_occlusion;
+pub mod antialiasing;
+pub mod bounds;
+pub mod bvh;
+pub mod camera;
+pub mod canvas;
+pub mod colors;
+pub mod environment;
+#[cfg(test)]
+mod fixtures;
+pub mod geometries;
+pub mod group;
+pub mod instance;
+pub mod intersections;
+pub mod lights;
+pub mod materials;
+pub mod matrix;
+pub mod mesh;
+pub mod microfacet;
+pub mod motion;
+pub mod packet;
+pub mod path_tracer;
+pub mod quaternion;
+pub mod ray;
+pub mod sampling;
+pub mod scalar;
+pub mod scenes;
+pub mod sky;
+pub mod transformations;
+pub mod utils;
+pub mod vectors;
+pub mod world;
@@ -1,23 +1,412 @@
 use crate::colors::Color;
-use crate::materials::Material;
-use crate::vectors::Tuple;
+use crate::materials::{Material, Shading};
+use crate::microfacet;
+use crate::vectors::{Normal3, Point3, Vector3};
+use std::f64::consts::PI;
+
+// a single point on a light as seen from the shaded point
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    pub direction: Vector3,
+    pub distance: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Attenuation {
+    // the light is equally strong at any distance
+    None,
+    // physically correct falloff, intensity is the radiant intensity (W/sr)
+    // and equals the irradiance one unit away from the light
+    InverseSquare,
+    // the classic 1 / (constant + linear * d + quadratic * d^2)
+    Custom {
+        constant: f64,
+        linear: f64,
+        quadratic: f64,
+    },
+}
+
+impl Attenuation {
+    pub fn factor(&self, distance: f64) -> f64 {
+        match *self {
+            Attenuation::None => 1.,
+            Attenuation::InverseSquare => 1. / (distance * distance),
+            Attenuation::Custom {
+                constant,
+                linear,
+                quadratic,
+            } => {
+                1. / (constant
+                    + linear * distance
+                    + quadratic * distance.powi(2))
+            }
+        }
+    }
+}
+
+pub trait Light {
+    fn intensity(&self) -> Color;
+
+    // how much of the light makes it over the distance to the point, unlike
+    // falloff this dims the ambient part too
+    fn attenuation(&self, _point: Point3) -> f64 {
+        1.
+    }
+
+    // normalized vector pointing from the point towards the light
+    fn direction_from(&self, point: Point3) -> Vector3;
+
+    // fraction of the intensity that reaches the point, the ambient part is
+    // not affected by it
+    fn falloff(&self, _point: Point3) -> f64 {
+        1.
+    }
+
+    // how far a shadow ray has to go before it reaches the light
+    fn distance_from(&self, point: Point3) -> f64;
+
+    // lights with a surface are shaded and shadowed from several points on
+    // them, everything else is just the one point
+    fn samples(&self, point: Point3) -> Vec<LightSample> {
+        vec![LightSample {
+            direction: self.direction_from(point),
+            distance: self.distance_from(point),
+        }]
+    }
+}
 
 pub struct PointLight {
     pub intensity: Color,
-    pub position: Tuple,
+    pub position: Point3,
+    pub attenuation: Attenuation,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> PointLight {
+        PointLight {
+            intensity,
+            position,
+            attenuation: Attenuation::None,
+        }
+    }
+
+    // a light bulb radiating `watts` evenly into every direction
+    pub fn with_power(
+        position: Point3,
+        color: Color,
+        watts: f64,
+    ) -> PointLight {
+        PointLight {
+            intensity: color * (watts / (4. * PI)),
+            position,
+            attenuation: Attenuation::InverseSquare,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn attenuation(&self, point: Point3) -> f64 {
+        self.attenuation.factor(self.distance_from(point))
+    }
+
+    fn direction_from(&self, point: Point3) -> Vector3 {
+        (self.position - point).normalize()
+    }
+
+    fn distance_from(&self, point: Point3) -> f64 {
+        (self.position - point).magnitude()
+    }
+}
+
+pub struct SpotLight {
+    pub intensity: Color,
+    pub position: Point3,
+    pub attenuation: Attenuation,
+    direction: Vector3,
+    cos_inner: f64,
+    cos_outer: f64,
+}
+
+impl SpotLight {
+    // angles are measured from the axis of the cone, full intensity inside
+    // the inner angle fading out to nothing at the outer one. The same
+    // angle twice gives a hard edge, an outer angle inside the inner one
+    // makes no sense
+    pub fn new(
+        position: Point3,
+        direction: Vector3,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color,
+    ) -> Result<SpotLight, String> {
+        if outer_angle < inner_angle {
+            return Err(format!(
+                "the outer angle {} is inside the inner angle {}",
+                outer_angle, inner_angle
+            ));
+        }
+
+        Ok(SpotLight {
+            intensity,
+            position,
+            attenuation: Attenuation::None,
+            direction: direction.normalize(),
+            cos_inner: inner_angle.cos(),
+            cos_outer: outer_angle.cos(),
+        })
+    }
+
+    // same as new but `watts` are spread over the solid angle of the cone
+    pub fn with_power(
+        position: Point3,
+        direction: Vector3,
+        inner_angle: f64,
+        outer_angle: f64,
+        color: Color,
+        watts: f64,
+    ) -> Result<SpotLight, String> {
+        let mut light = SpotLight::new(
+            position,
+            direction,
+            inner_angle,
+            outer_angle,
+            color,
+        )?;
+        let solid_angle = 2. * PI * (1. - light.cos_outer);
+
+        light.intensity = color * (watts / solid_angle);
+        light.attenuation = Attenuation::InverseSquare;
+        Ok(light)
+    }
+}
+
+impl Light for SpotLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn attenuation(&self, point: Point3) -> f64 {
+        self.attenuation.factor(self.distance_from(point))
+    }
+
+    fn direction_from(&self, point: Point3) -> Vector3 {
+        (self.position - point).normalize()
+    }
+
+    fn falloff(&self, point: Point3) -> f64 {
+        let cos_angle = (-self.direction_from(point)).dot(&self.direction);
+        smoothstep(self.cos_outer, self.cos_inner, cos_angle)
+    }
+
+    fn distance_from(&self, point: Point3) -> f64 {
+        (self.position - point).magnitude()
+    }
+}
+
+// a light infinitely far away like the sun, all of its rays are parallel
+pub struct DirectionalLight {
+    pub intensity: Color,
+    direction: Vector3,
+}
+
+impl DirectionalLight {
+    // direction is the way the light travels, not where it comes from
+    pub fn new(direction: Vector3, intensity: Color) -> DirectionalLight {
+        DirectionalLight {
+            intensity,
+            direction: direction.normalize(),
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn direction_from(&self, _point: Point3) -> Vector3 {
+        -self.direction
+    }
+
+    fn distance_from(&self, _point: Point3) -> f64 {
+        f64::INFINITY
+    }
+}
+
+// rectangular light split up into usteps x vsteps cells, each cell is
+// sampled at a random spot inside of it when jitter is on
+pub struct AreaLight {
+    pub intensity: Color,
+    pub corner: Point3,
+    pub uvec: Vector3,
+    pub usteps: usize,
+    pub vvec: Vector3,
+    pub vsteps: usize,
+    pub position: Point3,
+    pub jitter: bool,
+    pub attenuation: Attenuation,
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Point3,
+        full_uvec: Vector3,
+        usteps: usize,
+        full_vvec: Vector3,
+        vsteps: usize,
+        intensity: Color,
+    ) -> Result<AreaLight, String> {
+        if usteps == 0 || vsteps == 0 {
+            return Err("an area light needs at least one cell".into());
+        }
+
+        Ok(AreaLight {
+            intensity,
+            corner,
+            uvec: full_uvec / usteps as f64,
+            usteps,
+            vvec: full_vvec / vsteps as f64,
+            vsteps,
+            position: corner + (full_uvec + full_vvec) / 2.,
+            jitter: true,
+            attenuation: Attenuation::None,
+        })
+    }
+
+    pub fn point_on_light(&self, u: usize, v: usize) -> Point3 {
+        let (ju, jv) = if self.jitter {
+            (rand::random::<f64>(), rand::random::<f64>())
+        } else {
+            (0.5, 0.5)
+        };
+
+        self.corner + self.uvec * (u as f64 + ju) + self.vvec * (v as f64 + jv)
+    }
+}
+
+impl Light for AreaLight {
+    fn intensity(&self) -> Color {
+        self.intensity
+    }
+
+    fn attenuation(&self, point: Point3) -> f64 {
+        self.attenuation.factor(self.distance_from(point))
+    }
+
+    fn direction_from(&self, point: Point3) -> Vector3 {
+        (self.position - point).normalize()
+    }
+
+    fn distance_from(&self, point: Point3) -> f64 {
+        (self.position - point).magnitude()
+    }
+
+    fn samples(&self, point: Point3) -> Vec<LightSample> {
+        let mut samples = Vec::with_capacity(self.usteps * self.vsteps);
+
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                let towards = self.point_on_light(u, v) - point;
+                samples.push(LightSample {
+                    direction: towards.normalize(),
+                    distance: towards.magnitude(),
+                });
+            }
+        }
+        samples
+    }
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    if edge0 >= edge1 {
+        // hard edged cone
+        return if x >= edge1 { 1. } else { 0. };
+    }
+
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0., 1.);
+    t * t * (3. - 2. * t)
 }
 
 pub fn lighting(
     m: Material,
-    light: &PointLight,
-    position: Tuple,
-    eye: Tuple,
-    normal: Tuple,
+    light: &dyn Light,
+    position: Point3,
+    eye: Vector3,
+    normal: Normal3,
+    light_intensity: f64,
+) -> Color {
+    let samples = light.samples(position);
+    lighting_samples(
+        m,
+        light,
+        position,
+        eye,
+        normal,
+        &samples,
+        light_intensity,
+    )
+}
+
+// same as lighting but shaded from samples that were already drawn, so the
+// ones tested for shadows are the ones the light comes from
+pub fn lighting_samples(
+    m: Material,
+    light: &dyn Light,
+    position: Point3,
+    eye: Vector3,
+    normal: Normal3,
+    samples: &[LightSample],
+    light_intensity: f64,
+) -> Color {
+    // light_intensity is the part of the light that isn't shadowed, from 0
+    // for a point in full shadow up to 1 when nothing is in the way
+    let arriving = light.intensity() * light.attenuation(position);
+    let ambient = m.color * arriving * m.ambient;
+    if light_intensity <= 0. {
+        return ambient;
+    }
+
+    let intensity = arriving * light.falloff(position);
+    let mut direct = Color::black();
+
+    for sample in samples {
+        direct = direct
+            + direct_lighting(m, intensity, sample.direction, eye, normal);
+    }
+
+    ambient + direct / samples.len() as f64 * light_intensity
+}
+
+// diffuse and specular light reflected towards the eye from light of the
+// given intensity arriving along lightv, without any ambient part
+pub fn direct_lighting(
+    m: Material,
+    intensity: Color,
+    lightv: Vector3,
+    eye: Vector3,
+    normal: Normal3,
 ) -> Color {
-    let effective_color = m.color * light.intensity;
+    match m.shading {
+        Shading::Phong => phong(m, intensity, lightv, eye, normal),
+        Shading::Microfacet => {
+            microfacet_lighting(m, intensity, lightv, eye, normal)
+        }
+    }
+}
+
+fn phong(
+    m: Material,
+    intensity: Color,
+    lightv: Vector3,
+    eye: Vector3,
+    normal: Normal3,
+) -> Color {
+    let effective_color = m.color * intensity;
 
-    let lightv = (light.position - position).normalize();
-    let ambient = effective_color * m.ambient;
     let mut diffuse = Color::black();
     let mut specular = Color::black();
 
@@ -29,98 +418,522 @@ pub fn lighting(
         let reflect_dot_eye = reflectv.dot(&eye);
 
         if reflect_dot_eye > 0. {
-            specular = light.intensity
-                * m.specular
-                * (reflect_dot_eye.powf(m.shininess))
+            specular =
+                intensity * m.specular * (reflect_dot_eye.powf(m.shininess))
         }
     }
-    ambient + diffuse + specular
+    diffuse + specular
+}
+
+fn microfacet_lighting(
+    m: Material,
+    intensity: Color,
+    lightv: Vector3,
+    eye: Vector3,
+    normal: Normal3,
+) -> Color {
+    let light_dot_normal = lightv.dot(&normal);
+    if light_dot_normal <= 0. {
+        return Color::black();
+    }
+
+    // the intensity is scaled by pi so that a white lambertian surface lit
+    // head on comes out white, same as with the phong model
+    let f = microfacet::brdf(&m, normal, lightv, eye);
+    f * intensity * (PI * light_dot_normal)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::colors::Color;
-    use crate::lights::{lighting, PointLight};
+    use crate::lights::{
+        lighting, AreaLight, Attenuation, DirectionalLight, Light, PointLight,
+        SpotLight,
+    };
     use crate::materials::Material;
-    use crate::vectors::Tuple;
+    use crate::utils::float_compare;
+    use crate::vectors::{Normal3, Point3, Vector3};
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
 
     #[test]
     fn test_light_between() {
         let m = Material::default();
-        let position = Tuple::point(0., 0., 0.);
-        let normal = Tuple::vector(0., 0., -1.);
-        let eyev = Tuple::vector(0., 0., -1.);
-        let light = PointLight {
-            intensity: Color::new(1., 1., 1.),
-            position: Tuple::point(0., 0., -10.),
-        };
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
+        let eyev = Vector3::new(0., 0., -1.);
+        let light =
+            PointLight::new(Point3::new(0., 0., -10.), Color::new(1., 1., 1.));
 
-        let r = lighting(m, &light, position, eyev, normal);
+        let r = lighting(m, &light, position, eyev, normal, 1.);
         assert_eq!(r, Color::new(1.9, 1.9, 1.9))
     }
 
     #[test]
     fn test_light_up() {
         let m = Material::default();
-        let position = Tuple::point(0., 0., 0.);
-        let normal = Tuple::vector(0., 0., -1.);
-
-        let sq2_2 = (2_f64).sqrt() / 2. as f64;
-        let eyev = Tuple::vector(0., sq2_2, sq2_2);
-        let light = PointLight {
-            intensity: Color::new(1., 1., 1.),
-            position: Tuple::point(0., 0., -10.),
-        };
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
+
+        let sq2_2 = (2_f64).sqrt() / 2.;
+        let eyev = Vector3::new(0., sq2_2, sq2_2);
+        let light =
+            PointLight::new(Point3::new(0., 0., -10.), Color::new(1., 1., 1.));
 
-        let r = lighting(m, &light, position, eyev, normal);
+        let r = lighting(m, &light, position, eyev, normal, 1.);
         assert_eq!(r, Color::new(1.0, 1.0, 1.0))
     }
 
     #[test]
     fn test_light_down() {
         let m = Material::default();
-        let position = Tuple::point(0., 0., 0.);
-        let normal = Tuple::vector(0., 0., -1.);
-        let eyev = Tuple::vector(0., 0., -1.);
-        let light = PointLight {
-            intensity: Color::new(1., 1., 1.),
-            position: Tuple::point(0., 10., -10.),
-        };
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
+        let eyev = Vector3::new(0., 0., -1.);
+        let light = PointLight::new(
+            Point3::new(0., 10., -10.),
+            Color::new(1., 1., 1.),
+        );
 
-        let r = lighting(m, &light, position, eyev, normal);
+        let r = lighting(m, &light, position, eyev, normal, 1.);
         assert_eq!(r, Color::new(0.7364, 0.7364, 0.7364))
     }
 
     #[test]
     fn test_light_pong() {
         let m = Material::default();
-        let position = Tuple::point(0., 0., 0.);
-        let normal = Tuple::vector(0., 0., -1.);
-
-        let sq2_2 = (2_f64).sqrt() / 2. as f64;
-        let eyev = Tuple::vector(0., -sq2_2, -sq2_2);
-        let light = PointLight {
-            intensity: Color::new(1., 1., 1.),
-            position: Tuple::point(0., 10., -10.),
-        };
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
 
-        let r = lighting(m, &light, position, eyev, normal);
+        let sq2_2 = (2_f64).sqrt() / 2.;
+        let eyev = Vector3::new(0., -sq2_2, -sq2_2);
+        let light = PointLight::new(
+            Point3::new(0., 10., -10.),
+            Color::new(1., 1., 1.),
+        );
+
+        let r = lighting(m, &light, position, eyev, normal, 1.);
         assert_eq!(r, Color::new(1.6364, 1.6364, 1.6364))
     }
 
     #[test]
     fn test_light_behind() {
         let m = Material::default();
-        let position = Tuple::point(0., 0., 0.);
-        let normal = Tuple::vector(0., 0., -1.);
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
+
+        let eyev = Vector3::new(0., 0., -1.);
+        let light =
+            PointLight::new(Point3::new(0., 0., 10.), Color::new(1., 1., 1.));
+
+        let r = lighting(m, &light, position, eyev, normal, 1.);
+        assert_eq!(r, Color::new(0.1, 0.1, 0.1))
+    }
+
+    #[test]
+    fn test_microfacet_selected() {
+        let mut m = Material::microfacet(Color::white(), 0., 1.);
+        m.ambient = 0.;
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
+        let eyev = Vector3::new(0., 0., -1.);
+        let light =
+            PointLight::new(Point3::new(0., 0., -10.), Color::new(1., 1., 1.));
 
-        let eyev = Tuple::vector(0., 0., -1.);
-        let light = PointLight {
-            intensity: Color::new(1., 1., 1.),
-            position: Tuple::point(0., 0., 10.),
+        let r = lighting(m, &light, position, eyev, normal, 1.);
+        assert!(
+            r != lighting(
+                Material::default(),
+                &light,
+                position,
+                eyev,
+                normal,
+                1.
+            )
+        );
+        assert!(r.max_component() > 0.8 && r.max_component() < 1.);
+    }
+
+    #[test]
+    fn test_microfacet_white_furnace() {
+        // surround the point with lights whose intensities add up to a
+        // uniform white sky, the reflected light must never exceed it
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
+        let steps = 64;
+        let d_theta = (PI / 2.) / steps as f64;
+        let d_phi = (2. * PI) / steps as f64;
+
+        for roughness in [0.4, 0.7, 1.] {
+            for metallic in [0., 1.] {
+                let mut m =
+                    Material::microfacet(Color::white(), metallic, roughness);
+                m.ambient = 0.;
+
+                for angle in [0., PI / 3.] {
+                    let eyev = Vector3::new(angle.sin(), 0., -angle.cos());
+                    let mut sum = Color::black();
+
+                    for i in 0..steps {
+                        let theta = (i as f64 + 0.5) * d_theta;
+                        for j in 0..steps {
+                            let phi = (j as f64 + 0.5) * d_phi;
+                            let solid_angle = theta.sin() * d_theta * d_phi;
+                            let light = PointLight::new(
+                                Point3::new(
+                                    theta.sin() * phi.cos(),
+                                    theta.sin() * phi.sin(),
+                                    -theta.cos(),
+                                ),
+                                Color::white() * (solid_angle / PI),
+                            );
+                            sum = sum
+                                + lighting(
+                                    m, &light, position, eyev, normal, 1.,
+                                );
+                        }
+                    }
+
+                    assert!(sum.max_component() <= 1.);
+                }
+            }
+        }
+    }
+
+    fn spot() -> SpotLight {
+        SpotLight::new(
+            Point3::new(0., 0., -10.),
+            Vector3::new(0., 0., 1.),
+            PI / 8.,
+            PI / 4.,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_spot_light_angles() {
+        let cone = |inner: f64, outer: f64| {
+            SpotLight::new(
+                Point3::new(0., 0., -10.),
+                Vector3::new(0., 0., 1.),
+                inner,
+                outer,
+                Color::white(),
+            )
         };
+        assert!(cone(PI / 4., PI / 8.).is_err());
+        assert!(cone(PI / 8., PI / 8.).is_ok());
+    }
+
+    #[test]
+    fn test_spot_light_falloff() {
+        let light = spot();
 
-        let r = lighting(m, &light, position, eyev, normal);
+        // on the axis and inside the inner cone
+        assert!(float_compare(light.falloff(Point3::new(0., 0., 0.)), 1.));
+        assert!(float_compare(light.falloff(Point3::new(1., 0., 0.)), 1.));
+
+        // outside of the outer cone
+        assert!(float_compare(light.falloff(Point3::new(20., 0., 0.)), 0.));
+
+        // behind the light
+        assert!(float_compare(light.falloff(Point3::new(0., 0., -20.)), 0.));
+
+        // somewhere in between the two cones
+        let between = light.falloff(Point3::new(7., 0., 0.));
+        assert!(between > 0. && between < 1.);
+    }
+
+    #[test]
+    fn test_spot_light_smooth() {
+        let light = spot();
+        let mut previous = 1.;
+
+        for i in 0..100 {
+            let x = 3. + 0.1 * i as f64;
+            let f = light.falloff(Point3::new(x, 0., 0.));
+            assert!(f <= previous);
+            previous = f;
+        }
+    }
+
+    #[test]
+    fn test_spot_light_lighting() {
+        let m = Material::default();
+        let light = spot();
+        let normal = Normal3::new(0., 0., -1.);
+        let eyev = Vector3::new(0., 0., -1.);
+
+        let lit =
+            lighting(m, &light, Point3::new(0., 0., 0.), eyev, normal, 1.);
+        assert_eq!(lit, Color::new(1.9, 1.9, 1.9));
+
+        // outside of the cone only the ambient light remains
+        let dark =
+            lighting(m, &light, Point3::new(20., 0., 0.), eyev, normal, 1.);
+        assert_eq!(dark, Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_light_in_shadow() {
+        let m = Material::default();
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
+        let eyev = Vector3::new(0., 0., -1.);
+        let light =
+            PointLight::new(Point3::new(0., 0., -10.), Color::new(1., 1., 1.));
+
+        let r = lighting(m, &light, position, eyev, normal, 0.);
         assert_eq!(r, Color::new(0.1, 0.1, 0.1))
     }
+
+    #[test]
+    fn test_directional_light() {
+        let light =
+            DirectionalLight::new(Vector3::new(0., -1., 1.), Color::white());
+        let sq2_2 = (2_f64).sqrt() / 2.;
+
+        // the light vector is the same no matter where the point is
+        for p in [Point3::new(0., 0., 0.), Point3::new(-400., 50., -10.)] {
+            assert_eq!(
+                light.direction_from(p),
+                Vector3::new(0., sq2_2, -sq2_2)
+            );
+            assert_eq!(light.distance_from(p), f64::INFINITY);
+        }
+    }
+
+    #[test]
+    fn test_directional_light_lighting() {
+        let m = Material::default();
+        let normal = Normal3::new(0., 0., -1.);
+        let eyev = Vector3::new(0., 0., -1.);
+        let light =
+            DirectionalLight::new(Vector3::new(0., 0., 1.), Color::white());
+
+        let near =
+            lighting(m, &light, Point3::new(0., 0., 0.), eyev, normal, 1.);
+        let far =
+            lighting(m, &light, Point3::new(1e6, 0., 1e3), eyev, normal, 1.);
+        assert_eq!(near, Color::new(1.9, 1.9, 1.9));
+        assert_eq!(far, near);
+    }
+
+    fn area_light() -> AreaLight {
+        let mut light = AreaLight::new(
+            Point3::new(0., 0., 0.),
+            Vector3::new(2., 0., 0.),
+            4,
+            Vector3::new(0., 0., 1.),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        light.jitter = false;
+        light
+    }
+
+    #[test]
+    fn test_area_light() {
+        let light = area_light();
+
+        assert_eq!(light.uvec, Vector3::new(0.5, 0., 0.));
+        assert_eq!(light.vvec, Vector3::new(0., 0., 0.5));
+        assert_eq!(light.position, Point3::new(1., 0., 0.5));
+        assert_eq!(light.samples(Point3::new(0., 5., 0.)).len(), 8);
+    }
+
+    #[test]
+    fn test_area_light_needs_cells() {
+        let light = AreaLight::new(
+            Point3::new(0., 0., 0.),
+            Vector3::new(2., 0., 0.),
+            0,
+            Vector3::new(0., 0., 1.),
+            2,
+            Color::new(1., 1., 1.),
+        );
+        assert!(light.is_err());
+    }
+
+    #[test]
+    fn test_point_on_light() {
+        let light = area_light();
+
+        assert_eq!(light.point_on_light(0, 0), Point3::new(0.25, 0., 0.25));
+        assert_eq!(light.point_on_light(1, 0), Point3::new(0.75, 0., 0.25));
+        assert_eq!(light.point_on_light(0, 1), Point3::new(0.25, 0., 0.75));
+        assert_eq!(light.point_on_light(2, 0), Point3::new(1.25, 0., 0.25));
+        assert_eq!(light.point_on_light(3, 1), Point3::new(1.75, 0., 0.75));
+    }
+
+    #[test]
+    fn test_point_on_light_jitter() {
+        let mut light = area_light();
+        light.jitter = true;
+
+        // the jittered point stays inside of its own cell
+        for _ in 0..100 {
+            let p = light.point_on_light(3, 1);
+            assert!(p.x >= 1.5 && p.x <= 2.);
+            assert!(p.z >= 0.5 && p.z <= 1.);
+        }
+    }
+
+    #[test]
+    fn test_lighting_fractional_intensity() {
+        let m = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.,
+            ..Material::default()
+        };
+        let position = Point3::new(0., 0., -1.);
+        let normal = Normal3::new(0., 0., -1.);
+        let eyev = Vector3::new(0., 0., -1.);
+        let light =
+            PointLight::new(Point3::new(0., 0., -10.), Color::new(1., 1., 1.));
+
+        for (intensity, expected) in [(1., 1.), (0.5, 0.55), (0., 0.1)] {
+            let r = lighting(m, &light, position, eyev, normal, intensity);
+            assert_eq!(r, Color::new(expected, expected, expected));
+        }
+    }
+
+    #[test]
+    fn test_lighting_samples_area_light() {
+        let m = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.,
+            ..Material::default()
+        };
+        let mut light = AreaLight::new(
+            Point3::new(-0.5, -0.5, -5.),
+            Vector3::new(1., 0., 0.),
+            2,
+            Vector3::new(0., 1., 0.),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        light.jitter = false;
+        let eye = Point3::new(0., 0., -5.);
+
+        let cases = [
+            (Point3::new(0., 0., -1.), 0.9965),
+            (Point3::new(0., FRAC_1_SQRT_2, -FRAC_1_SQRT_2), 0.62318),
+        ];
+
+        for (point, expected) in cases {
+            let eyev = (eye - point).normalize();
+            let normal = Normal3::new(point.x, point.y, point.z);
+            let r = lighting(m, &light, point, eyev, normal, 1.);
+            assert_eq!(r, Color::new(expected, expected, expected));
+        }
+    }
+
+    #[test]
+    fn test_attenuation_factor() {
+        assert!(float_compare(Attenuation::None.factor(500.), 1.));
+        assert!(float_compare(Attenuation::InverseSquare.factor(1.), 1.));
+        assert!(float_compare(Attenuation::InverseSquare.factor(2.), 0.25));
+
+        let custom = Attenuation::Custom {
+            constant: 1.,
+            linear: 0.5,
+            quadratic: 0.25,
+        };
+        assert!(float_compare(custom.factor(0.), 1.));
+        assert!(float_compare(custom.factor(2.), 1. / 3.));
+    }
+
+    // white surface at the origin facing -z without any specular highlight,
+    // so the result is easy to predict
+    fn lit_from(light: &dyn Light) -> Color {
+        let m = Material {
+            specular: 0.,
+            ..Material::default()
+        };
+        let position = Point3::new(0., 0., 0.);
+        let normal = Normal3::new(0., 0., -1.);
+        let eyev = Vector3::new(0., 0., -1.);
+
+        lighting(m, light, position, eyev, normal, 1.)
+    }
+
+    #[test]
+    fn test_lighting_no_attenuation() {
+        let near = PointLight::new(Point3::new(0., 0., -5.), Color::white());
+        let far = PointLight::new(Point3::new(0., 0., -500.), Color::white());
+
+        assert_eq!(lit_from(&near), Color::new(1., 1., 1.));
+        assert_eq!(lit_from(&far), lit_from(&near));
+    }
+
+    #[test]
+    fn test_lighting_inverse_square() {
+        let mut near =
+            PointLight::new(Point3::new(0., 0., -1.), Color::white());
+        near.attenuation = Attenuation::InverseSquare;
+        let mut far =
+            PointLight::new(Point3::new(0., 0., -2.), Color::white());
+        far.attenuation = Attenuation::InverseSquare;
+
+        assert_eq!(lit_from(&near), Color::new(1., 1., 1.));
+        assert_eq!(lit_from(&far), Color::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn test_lighting_custom_attenuation() {
+        let mut light =
+            PointLight::new(Point3::new(0., 0., -2.), Color::white());
+        light.attenuation = Attenuation::Custom {
+            constant: 1.,
+            linear: 0.5,
+            quadratic: 0.25,
+        };
+
+        let third = 1. / 3.;
+        assert_eq!(lit_from(&light), Color::new(third, third, third));
+    }
+
+    #[test]
+    fn test_lighting_attenuated_spot_light() {
+        let mut light = SpotLight::new(
+            Point3::new(0., 0., -2.),
+            Vector3::new(0., 0., 1.),
+            PI / 8.,
+            PI / 4.,
+            Color::white(),
+        )
+        .unwrap();
+        light.attenuation = Attenuation::InverseSquare;
+
+        assert_eq!(lit_from(&light), Color::new(0.25, 0.25, 0.25));
+    }
+
+    #[test]
+    fn test_light_power() {
+        // 4 pi watts spread over the whole sphere are 1 W/sr
+        let bulb = PointLight::with_power(
+            Point3::new(0., 0., -1.),
+            Color::white(),
+            4. * PI,
+        );
+        assert_eq!(bulb.intensity, Color::new(1., 1., 1.));
+        assert_eq!(lit_from(&bulb), Color::new(1., 1., 1.));
+
+        // the same power focused into a cone is a lot brighter
+        let spot = SpotLight::with_power(
+            Point3::new(0., 0., -1.),
+            Vector3::new(0., 0., 1.),
+            PI / 8.,
+            PI / 4.,
+            Color::white(),
+            4. * PI,
+        )
+        .unwrap();
+        assert!(lit_from(&spot).max_component() > 5.);
+    }
 }
@@ -1,15 +1,4 @@
-mod canvas;
-pub mod colors;
-mod geometries;
-mod intersections;
-mod lights;
-mod materials;
-mod matrix;
-mod ray;
-mod scenes;
-mod transformations;
-mod utils;
-pub mod vectors;
+use paprskomet::scenes;
 
 fn main() {
     // scenes::write_projectile_image();
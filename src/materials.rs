@@ -1,4 +1,14 @@
 use crate::colors::Color;
+use crate::utils::float_compare;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shading {
+    // the book's ambient + diffuse + specular model
+    Phong,
+    // GGX / Cook-Torrance driven by metallic, roughness and fresnel
+    Microfacet,
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Material {
@@ -7,16 +17,301 @@ pub struct Material {
     pub diffuse: f64,
     pub specular: f64,
     pub shininess: f64,
+    // light given off by the surface itself, added on top of any lighting
+    pub emissive: Color,
+    pub reflective: f64,
+    pub transparency: f64,
+    pub refractive_index: f64,
+
+    pub shading: Shading,
+    pub metallic: f64,
+    pub roughness: f64,
+    // reflectance of a dielectric at normal incidence (F0)
+    pub fresnel: f64,
 }
 
-impl Material {
-    pub fn default() -> Material {
+// the book's defaults, callers build on it with struct update syntax
+impl Default for Material {
+    fn default() -> Self {
         Material {
             color: Color::new(1., 1., 1.),
             ambient: 0.1,
             diffuse: 0.9,
             specular: 0.9,
             shininess: 200.,
+            emissive: Color::black(),
+            reflective: 0.,
+            transparency: 0.,
+            refractive_index: 1.,
+
+            shading: Shading::Phong,
+            metallic: 0.,
+            roughness: 0.5,
+            fresnel: 0.04,
+        }
+    }
+}
+
+impl Material {
+    pub fn microfacet(
+        color: Color,
+        metallic: f64,
+        roughness: f64,
+    ) -> Material {
+        Material {
+            color,
+            shading: Shading::Microfacet,
+            metallic,
+            roughness,
+            ..Material::default()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let unit_fields = [
+            ("ambient", self.ambient),
+            ("diffuse", self.diffuse),
+            ("specular", self.specular),
+            ("metallic", self.metallic),
+            ("roughness", self.roughness),
+            ("fresnel", self.fresnel),
+            ("reflective", self.reflective),
+            ("transparency", self.transparency),
+        ];
+
+        for (name, value) in unit_fields {
+            if !(0. ..=1.).contains(&value) {
+                return Err(format!("{} has to be between 0 and 1", name));
+            }
+        }
+
+        // more than that and the surface reflects more light than it gets
+        let total = self.ambient + self.diffuse;
+        if total > 1. && !float_compare(total, 1.) {
+            return Err("ambient + diffuse can't be more than 1".into());
+        }
+
+        // the path tracer picks one of them for every bounce, so they split
+        // the light between them
+        let total = self.reflective + self.transparency;
+        if total > 1. && !float_compare(total, 1.) {
+            return Err(
+                "reflective + transparency can't be more than 1".into()
+            );
+        }
+
+        if self.shininess <= 0. {
+            return Err("shininess has to be positive".into());
+        }
+
+        if self.refractive_index < 1. {
+            return Err("refractive index can't be less than 1".into());
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MaterialLibrary {
+    materials: HashMap<String, Material>,
+}
+
+impl Default for MaterialLibrary {
+    fn default() -> Self {
+        MaterialLibrary::new()
+    }
+}
+
+impl MaterialLibrary {
+    pub fn new() -> MaterialLibrary {
+        MaterialLibrary {
+            materials: HashMap::new(),
+        }
+    }
+
+    pub fn with_presets() -> MaterialLibrary {
+        let mut library = MaterialLibrary::new();
+
+        let glass = Material {
+            color: Color::black(),
+            ambient: 0.,
+            diffuse: 0.1,
+            specular: 1.,
+            shininess: 300.,
+            reflective: 0.1,
+            transparency: 0.9,
+            refractive_index: 1.5,
+            ..Material::default()
+        };
+        library.define("glass", glass).unwrap();
+
+        library
+            .extend("water", "glass", |m| {
+                m.color = Color::new(0., 0.05, 0.08);
+                m.refractive_index = 1.333;
+            })
+            .unwrap();
+
+        library
+            .extend("diamond", "glass", |m| {
+                m.reflective = 0.2;
+                m.transparency = 0.8;
+                m.refractive_index = 2.417;
+            })
+            .unwrap();
+
+        let chrome = Material {
+            ambient: 0.,
+            reflective: 0.8,
+            ..Material::microfacet(Color::new(0.55, 0.56, 0.55), 1., 0.1)
+        };
+        library.define("chrome", chrome).unwrap();
+
+        let matte = Material {
+            specular: 0.,
+            ..Material::default()
+        };
+        library.define("matte", matte).unwrap();
+
+        let plastic = Material {
+            diffuse: 0.7,
+            specular: 0.6,
+            shininess: 80.,
+            reflective: 0.05,
+            ..Material::default()
+        };
+        library.define("plastic", plastic).unwrap();
+
+        library
+    }
+
+    pub fn get(&self, name: &str) -> Option<Material> {
+        self.materials.get(name).copied()
+    }
+
+    pub fn define(&mut self, name: &str, m: Material) -> Result<(), String> {
+        m.validate().map_err(|e| format!("{}: {}", name, e))?;
+        self.materials.insert(name.into(), m);
+        Ok(())
+    }
+
+    pub fn extend<F>(
+        &mut self,
+        name: &str,
+        base: &str,
+        overrides: F,
+    ) -> Result<(), String>
+    where
+        F: FnOnce(&mut Material),
+    {
+        // works like `extend` in cover.yml, start from a copy of the base
+        // and only change the fields that differ
+        let mut m = match self.get(base) {
+            Some(m) => m,
+            None => {
+                return Err(format!("{}: unknown material {}", name, base))
+            }
+        };
+        overrides(&mut m);
+        self.define(name, m)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::materials::{Material, MaterialLibrary, Shading};
+    use crate::utils::float_compare;
+
+    #[test]
+    fn test_default_material() {
+        let m = Material::default();
+        assert!(float_compare(m.reflective, 0.));
+        assert!(float_compare(m.transparency, 0.));
+        assert!(float_compare(m.refractive_index, 1.));
+        assert!(m.validate().is_ok());
+    }
+
+    #[test]
+    fn test_presets() {
+        let library = MaterialLibrary::with_presets();
+
+        for name in ["glass", "water", "diamond", "chrome", "matte", "plastic"]
+        {
+            assert!(library.get(name).is_some(), "missing {}", name);
         }
+
+        assert!(library.get("potato").is_none());
+        assert_eq!(
+            library.get("chrome").unwrap().shading,
+            Shading::Microfacet
+        );
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut library = MaterialLibrary::with_presets();
+        library
+            .extend("red-plastic", "plastic", |m| {
+                m.color = Color::new(1., 0., 0.);
+            })
+            .unwrap();
+
+        let base = library.get("plastic").unwrap();
+        let red = library.get("red-plastic").unwrap();
+
+        assert_eq!(red.color, Color::new(1., 0., 0.));
+        assert_eq!(base.color, Color::new(1., 1., 1.));
+        assert!(float_compare(red.shininess, base.shininess));
+        assert!(float_compare(red.specular, base.specular));
+    }
+
+    #[test]
+    fn test_extend_unknown_base() {
+        let mut library = MaterialLibrary::new();
+        let r = library.extend("red", "potato", |m| m.ambient = 0.);
+
+        assert!(r.is_err());
+        assert!(library.get("red").is_none());
+    }
+
+    #[test]
+    fn test_blown_out_material_rejected() {
+        let mut library = MaterialLibrary::with_presets();
+        let r = library.extend("bright", "matte", |m| m.diffuse = 1.6);
+
+        assert!(r.is_err());
+        assert!(library.get("bright").is_none());
+    }
+
+    #[test]
+    fn test_validate() {
+        let m = Material {
+            refractive_index: 0.5,
+            ..Material::default()
+        };
+        assert!(m.validate().is_err());
+
+        let m = Material {
+            shininess: 0.,
+            ..Material::default()
+        };
+        assert!(m.validate().is_err());
+
+        let m = Material {
+            roughness: 1.5,
+            ..Material::default()
+        };
+        assert!(m.validate().is_err());
+
+        let mut m = Material {
+            reflective: 0.9,
+            transparency: 0.9,
+            ..Material::default()
+        };
+        assert!(m.validate().is_err());
+        m.reflective = 0.1;
+        assert!(m.validate().is_ok());
     }
 }
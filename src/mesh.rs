@@ -0,0 +1,592 @@
+use crate::bounds::BoundingBox;
+use crate::bvh::Bvh;
+use crate::geometries::{Shape, ShapeId};
+use crate::intersections::{Intersection, Intersections};
+use crate::materials::Material;
+use crate::matrix::M;
+use crate::packet::{keep_closer, Hits, Mask, RayPacket, LANES};
+use crate::ray::Ray;
+use crate::scalar::Scalar;
+use crate::vectors::{Normal3, Point3, Vector3};
+
+// a triangle mesh kept as plain buffers, every vertex is stored once and
+// the triangles only refer to them by index. Hits carry the index of the
+// triangle in `primitive`. The buffers can be kept in f32 to halve the
+// memory of huge meshes, everything is worked out in f64 once read
+pub struct Mesh<T: Scalar = f64> {
+    pub id: ShapeId,
+    transform: M,
+    transform_inv: M,
+
+    positions: Vec<[T; 3]>,
+    // one per position, without them every triangle is flat
+    normals: Vec<[T; 3]>,
+    uvs: Vec<[T; 2]>,
+    triangles: Vec<[u32; 3]>,
+    bvh: Bvh,
+
+    pub material: Material,
+}
+
+impl<T: Scalar> Mesh<T> {
+    pub fn new(
+        positions: Vec<[T; 3]>,
+        triangles: Vec<[u32; 3]>,
+    ) -> Result<Mesh<T>, String> {
+        if let Some(t) = triangles
+            .iter()
+            .find(|t| t.iter().any(|&i| i as usize >= positions.len()))
+        {
+            return Err(format!(
+                "triangle {:?} refers to a missing vertex, there are only {}",
+                t,
+                positions.len()
+            ));
+        }
+
+        // a triangle without area has no normal, and the ray tests and
+        // barycentric weights divide by zero on it
+        let has_area = |t: &[u32; 3]| {
+            let [a, b, c] = t.map(|i| point(positions[i as usize]));
+            (b - a).cross(&(c - a)).magnitude() > 0.
+        };
+        if let Some(t) = triangles.iter().find(|t| !has_area(t)) {
+            return Err(format!("triangle {:?} has no area", t));
+        }
+
+        let bounds: Vec<BoundingBox> = triangles
+            .iter()
+            .map(|t| {
+                let mut b = BoundingBox::empty();
+                for &i in t {
+                    b.add_point(point(positions[i as usize]));
+                }
+                b
+            })
+            .collect();
+
+        Ok(Mesh {
+            id: ShapeId::NONE,
+            transform: M::ident(4),
+            transform_inv: M::ident(4),
+            positions,
+            normals: vec![],
+            uvs: vec![],
+            triangles,
+            bvh: Bvh::build(&bounds),
+            material: Material::default(),
+        })
+    }
+
+    // vertex normals for smooth shading, in the same order as the positions
+    pub fn with_normals(
+        mut self,
+        normals: Vec<[T; 3]>,
+    ) -> Result<Mesh<T>, String> {
+        if normals.len() != self.positions.len() {
+            return Err(format!(
+                "{} normals given for {} vertices",
+                normals.len(),
+                self.positions.len()
+            ));
+        }
+        self.normals = normals;
+        Ok(self)
+    }
+
+    pub fn with_uvs(mut self, uvs: Vec<[T; 2]>) -> Result<Mesh<T>, String> {
+        if uvs.len() != self.positions.len() {
+            return Err(format!(
+                "{} texture coordinates given for {} vertices",
+                uvs.len(),
+                self.positions.len()
+            ));
+        }
+        self.uvs = uvs;
+        Ok(self)
+    }
+
+    pub fn set_transform(&mut self, m: M) {
+        self.transform = m.clone();
+        self.transform_inv = m.inverse();
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    // texture coordinates where the hit landed, if the mesh has any
+    pub fn uv_at(&self, p: Point3, hit: &Intersection) -> Option<(f64, f64)> {
+        if self.uvs.is_empty() {
+            return None;
+        }
+
+        let t = self.triangles[hit.primitive];
+        let (u, v) = self.barycentric(hit.primitive, &self.transform_inv * p);
+        let w = 1. - u - v;
+        let [a, b, c] = t.map(|i| self.uvs[i as usize].map(T::to_f64));
+        Some((
+            a[0] * w + b[0] * u + c[0] * v,
+            a[1] * w + b[1] * u + c[1] * v,
+        ))
+    }
+
+    fn vertex(&self, i: u32) -> Point3 {
+        point(self.positions[i as usize])
+    }
+
+    fn corners(&self, triangle: usize) -> (Point3, Point3, Point3) {
+        let [a, b, c] = self.triangles[triangle];
+        (self.vertex(a), self.vertex(b), self.vertex(c))
+    }
+
+    // moller and trumbore, in the mesh's own coordinates
+    fn intersect_triangle(&self, triangle: usize, ray: Ray) -> Option<f64> {
+        let (p1, p2, p3) = self.corners(triangle);
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+
+        // scanned models have tiny triangles, anything that isn't exactly
+        // parallel is worth testing
+        let dir_cross_e2 = ray.direction.cross(&e2);
+        let det = e1.dot(&dir_cross_e2);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+
+        let f = 1. / det;
+        let p1_to_origin = ray.origin - p1;
+        let u = f * p1_to_origin.dot(&dir_cross_e2);
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+
+        let origin_cross_e1 = p1_to_origin.cross(&e1);
+        let v = f * ray.direction.dot(&origin_cross_e1);
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+
+        Some(f * e2.dot(&origin_cross_e1))
+    }
+
+    // the same test for every lane in the mask, NaN where a lane misses
+    fn intersect_triangle_lanes(
+        &self,
+        triangle: usize,
+        packet: &RayPacket,
+        mask: Mask,
+    ) -> [f64; LANES] {
+        let (p1, p2, p3) = self.corners(triangle);
+        let p1 = [p1.x, p1.y, p1.z];
+        let e1 = [p2.x - p1[0], p2.y - p1[1], p2.z - p1[2]];
+        let e2 = [p3.x - p1[0], p3.y - p1[1], p3.z - p1[2]];
+        let (o, d) = (&packet.origin, &packet.direction);
+
+        let mut ts = [f64::NAN; LANES];
+        for l in (0..LANES).filter(|&l| mask[l]) {
+            let dir = [d[0][l], d[1][l], d[2][l]];
+            let dir_cross_e2 = cross(dir, e2);
+            let det = dot(e1, dir_cross_e2);
+            if det.abs() < 1e-12 {
+                continue;
+            }
+
+            let f = 1. / det;
+            let p1_to_origin =
+                [o[0][l] - p1[0], o[1][l] - p1[1], o[2][l] - p1[2]];
+            let u = f * dot(p1_to_origin, dir_cross_e2);
+            if !(0. ..=1.).contains(&u) {
+                continue;
+            }
+
+            let origin_cross_e1 = cross(p1_to_origin, e1);
+            let v = f * dot(dir, origin_cross_e1);
+            if v < 0. || u + v > 1. {
+                continue;
+            }
+            ts[l] = f * dot(e2, origin_cross_e1);
+        }
+        ts
+    }
+
+    // weights of the second and third corner at a point on the triangle
+    fn barycentric(&self, triangle: usize, p: Point3) -> (f64, f64) {
+        let (p1, p2, p3) = self.corners(triangle);
+        let (e1, e2, d) = (p2 - p1, p3 - p1, p - p1);
+
+        let (d11, d12, d22) = (e1.dot(&e1), e1.dot(&e2), e2.dot(&e2));
+        let (d1, d2) = (d.dot(&e1), d.dot(&e2));
+        let denominator = d11 * d22 - d12 * d12;
+        (
+            (d22 * d1 - d12 * d2) / denominator,
+            (d11 * d2 - d12 * d1) / denominator,
+        )
+    }
+
+    fn local_normal(&self, triangle: usize, p: Point3) -> Normal3 {
+        if self.normals.is_empty() {
+            let (p1, p2, p3) = self.corners(triangle);
+            let flat = (p3 - p1).cross(&(p2 - p1)).normalize();
+            return Normal3::from_vector(flat);
+        }
+
+        let [a, b, c] = self.triangles[triangle];
+        let normal = |i: u32| {
+            let [x, y, z] = self.normals[i as usize].map(T::to_f64);
+            Vector3::new(x, y, z)
+        };
+        let (u, v) = self.barycentric(triangle, p);
+        Normal3::from_vector(
+            normal(a) * (1. - u - v) + normal(b) * u + normal(c) * v,
+        )
+    }
+
+    fn to_world(&self, local_normal: Normal3) -> Normal3 {
+        local_normal.transform(&self.transform_inv).normalize()
+    }
+}
+
+impl<T: Scalar + 'static> Shape for Mesh<T> {
+    fn id(&self) -> ShapeId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ShapeId) {
+        self.id = id;
+    }
+
+    fn transform(&self) -> &M {
+        &self.transform
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> &mut Material {
+        &mut self.material
+    }
+
+    fn intersect_into(&self, ray_original: Ray, xs: &mut Intersections) {
+        let ray = ray_original.transform(&self.transform_inv);
+
+        self.bvh.candidates(ray, |i| {
+            if let Some(t) = self.intersect_triangle(i, ray) {
+                xs.push(Intersection {
+                    object_id: self.id,
+                    t,
+                    primitive: i,
+                });
+            }
+        });
+    }
+
+    fn intersect_packet(
+        &self,
+        packet: &RayPacket,
+        mask: Mask,
+        hits: &mut Hits,
+    ) {
+        let local = packet.transform(&self.transform_inv);
+        // the transform leaves t alone, so the hits so far bound the search
+        let mut limit = [f64::INFINITY; LANES];
+        for l in 0..LANES {
+            if let Some(h) = hits[l] {
+                limit[l] = h.t;
+            }
+        }
+
+        let bvh = &self.bvh;
+        bvh.closest_packet(&local, mask, &mut limit, |i, active, limit| {
+            let ts = self.intersect_triangle_lanes(i, &local, active);
+            for l in (0..LANES).filter(|&l| active[l]) {
+                keep_closer(
+                    hits,
+                    l,
+                    Intersection {
+                        object_id: self.id,
+                        t: ts[l],
+                        primitive: i,
+                    },
+                );
+                if let Some(h) = hits[l] {
+                    limit[l] = h.t;
+                }
+            }
+        });
+    }
+
+    // without the hit the triangle the point lies on has to be searched for
+    // one by one, shading goes through normal_at_hit instead
+    fn normal_at(&self, p: Point3, _time: f64) -> Normal3 {
+        let local = &self.transform_inv * p;
+        let distance = |triangle: usize| {
+            let (p1, p2, p3) = self.corners(triangle);
+            let (u, v) = self.barycentric(triangle, local);
+            let on = p1 + (p2 - p1) * u + (p3 - p1) * v;
+            let outside = (-u).max(-v).max(u + v - 1.).max(0.);
+            (local - on).magnitude() + outside
+        };
+
+        let closest = (0..self.triangles.len())
+            .min_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+            .expect("a mesh without triangles has no normals");
+        self.to_world(self.local_normal(closest, local))
+    }
+
+    fn normal_at_hit(
+        &self,
+        p: Point3,
+        hit: &Intersection,
+        _time: f64,
+    ) -> Normal3 {
+        let local = &self.transform_inv * p;
+        self.to_world(self.local_normal(hit.primitive, local))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let mut b = BoundingBox::empty();
+        for &p in &self.positions {
+            b.add_point(point(p));
+        }
+        b
+    }
+
+    fn into_parts(self: Box<Self>) -> Vec<Box<dyn Shape>> {
+        vec![self]
+    }
+
+    fn transform_by(&mut self, m: &M) {
+        self.set_transform(m * &self.transform);
+    }
+}
+
+fn point<T: Scalar>(p: [T; 3]) -> Point3 {
+    let [x, y, z] = p.map(T::to_f64);
+    Point3::new(x, y, z)
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fixtures;
+    use crate::geometries::Shape;
+    use crate::instance::Instance;
+    use crate::intersections::{hit, prepare_computations};
+    use crate::mesh::Mesh;
+    use crate::ray::Ray;
+    use crate::transformations::translation;
+    use crate::utils::float_compare;
+    use crate::vectors::{Normal3, Point3, Vector3};
+    use std::f64::consts::FRAC_1_SQRT_2;
+    use std::sync::Arc;
+
+    fn triangle() -> Mesh {
+        Mesh::new(
+            vec![[0., 1., 0.], [-1., 0., 0.], [1., 0., 0.]],
+            vec![[0, 1, 2]],
+        )
+        .unwrap()
+    }
+
+    // n by n squares in the xy plane, two triangles each
+    fn grid(n: u32) -> Mesh {
+        let mut positions = vec![];
+        for y in 0..=n {
+            for x in 0..=n {
+                positions.push([x as f64, y as f64, 0.]);
+            }
+        }
+
+        let mut triangles = vec![];
+        for y in 0..n {
+            for x in 0..n {
+                let i = y * (n + 1) + x;
+                triangles.push([i, i + 1, i + n + 2]);
+                triangles.push([i, i + n + 2, i + n + 1]);
+            }
+        }
+        Mesh::new(positions, triangles).unwrap()
+    }
+
+    #[test]
+    fn test_bad_indices() {
+        assert!(Mesh::new(vec![[0., 0., 0.]], vec![[0, 0, 1]]).is_err());
+        assert!(triangle().with_normals(vec![[0., 0., 1.]]).is_err());
+        assert!(triangle().with_uvs(vec![[0., 0.]; 3]).is_ok());
+    }
+
+    #[test]
+    fn test_degenerate_triangles() {
+        let positions = vec![[0., 0., 0.], [1., 0., 0.], [2., 0., 0.]];
+        // all three corners on a line
+        assert!(Mesh::new(positions.clone(), vec![[0, 1, 2]]).is_err());
+        // the same corner twice
+        assert!(Mesh::new(positions, vec![[0, 1, 1]]).is_err());
+        let broken = vec![[0., 0., 0.], [1., f64::NAN, 0.], [0., 1., 0.]];
+        assert!(Mesh::new(broken, vec![[0, 1, 2]]).is_err());
+    }
+
+    #[test]
+    fn test_triangle_misses() {
+        let t = triangle();
+        let cases = [
+            // parallel to the triangle
+            (Point3::new(0., -1., -2.), Vector3::new(0., 1., 0.)),
+            // past each of the three edges
+            (Point3::new(1., 1., -2.), Vector3::new(0., 0., 1.)),
+            (Point3::new(-1., 1., -2.), Vector3::new(0., 0., 1.)),
+            (Point3::new(0., -1., -2.), Vector3::new(0., 0., 1.)),
+        ];
+        for (origin, direction) in cases {
+            assert!(t.intersects(Ray::new(origin, direction)).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_triangle_hit() {
+        let t = triangle();
+        let r = Ray::new(Point3::new(0., 0.5, -2.), Vector3::new(0., 0., 1.));
+        let ints = t.intersects(r);
+        assert_eq!(ints.len(), 1);
+        assert_eq!(ints[0].t, 2.);
+        assert_eq!(ints[0].primitive, 0);
+
+        // the flat normal comes from the winding of the corners
+        assert_eq!(
+            t.normal_at_hit(Point3::new(0., 0.5, 0.), &ints[0], 0.),
+            Normal3::new(0., 0., -1.)
+        );
+    }
+
+    #[test]
+    fn test_primitive_index() {
+        let mut m = grid(10);
+        m.set_transform(translation(0., 0., 5.));
+        assert_eq!(m.triangle_count(), 200);
+
+        // the lower right half of the square at x = 3, y = 7
+        let r =
+            Ray::new(Point3::new(3.75, 7.25, -5.), Vector3::new(0., 0., 1.));
+        let i = hit(&m.intersects(r)).unwrap();
+        assert_eq!(i.t, 10.);
+        assert_eq!(i.primitive, 2 * (7 * 10 + 3));
+    }
+
+    #[test]
+    fn test_smooth_normals() {
+        let m = triangle()
+            .with_normals(vec![[0., 1., 0.], [-1., 0., 0.], [1., 0., 0.]])
+            .unwrap();
+        let r =
+            Ray::new(Point3::new(-0.2, 0.3, -2.), Vector3::new(0., 0., 1.));
+        let i = hit(&m.intersects(r)).unwrap();
+        let comps = prepare_computations(i, r, &m);
+
+        // the weights are 0.3 for the top corner and 0.45 and 0.25 for the
+        // bottom ones
+        let expected = Normal3::new(-0.2, 0.3, 0.).normalize();
+        assert_eq!(comps.normalv, expected);
+        assert_eq!(m.normal_at(comps.point, 0.), expected);
+    }
+
+    #[test]
+    fn test_uvs() {
+        let m = triangle()
+            .with_uvs(vec![[0.5, 1.], [0., 0.], [1., 0.]])
+            .unwrap();
+        let r = Ray::new(Point3::new(0., 0.5, -2.), Vector3::new(0., 0., 1.));
+        let i = hit(&m.intersects(r)).unwrap();
+        let (u, v) = m.uv_at(r.position(i.t), &i).unwrap();
+        assert!(float_compare(u, 0.5));
+        assert!(float_compare(v, 0.5));
+
+        assert!(triangle().uv_at(r.position(i.t), &i).is_none());
+    }
+
+    #[test]
+    fn test_single_precision_buffers() {
+        let m = Mesh::<f32>::new(
+            vec![[0., 1., 0.], [-1., 0., 0.], [1., 0., 0.]],
+            vec![[0, 1, 2]],
+        )
+        .unwrap()
+        .with_normals(vec![[0., 1., 0.], [-1., 0., 0.], [1., 0., 0.]])
+        .unwrap()
+        .with_uvs(vec![[0.5, 1.], [0., 0.], [1., 0.]])
+        .unwrap();
+
+        // the same hit and shading as the f64 triangle above
+        let r =
+            Ray::new(Point3::new(-0.2, 0.3, -2.), Vector3::new(0., 0., 1.));
+        let i = hit(&m.intersects(r)).unwrap();
+        assert_eq!(i.t, 2.);
+        let comps = prepare_computations(i, r, &m);
+        assert_eq!(comps.normalv, Normal3::new(-0.2, 0.3, 0.).normalize());
+        let (u, v) = m.uv_at(comps.point, &i).unwrap();
+        assert!(float_compare(u, 0.4));
+        assert!(float_compare(v, 0.3));
+    }
+
+    #[test]
+    fn test_mesh_bounds() {
+        let mut m = grid(4);
+        m.set_transform(translation(1., 2., 3.));
+        let b = m.parent_space_bounds();
+        assert_eq!(b.min, Point3::new(1., 2., 3.));
+        assert_eq!(b.max, Point3::new(5., 6., 3.));
+    }
+
+    #[test]
+    fn test_mesh_bvh_matches_brute_force() {
+        let mut rng = fixtures::rng();
+        let m = grid(20);
+        for _ in 0..200 {
+            let r = fixtures::ray_forward(
+                &mut rng,
+                Point3::new(0., 0., -5.),
+                Point3::new(20., 20., -5.),
+            );
+
+            let brute: Vec<usize> = (0..m.triangle_count())
+                .filter(|&i| m.intersect_triangle(i, r).is_some())
+                .collect();
+            let mut found: Vec<usize> =
+                m.intersects(r).iter().map(|i| i.primitive).collect();
+            found.sort_unstable();
+            assert_eq!(found, brute);
+        }
+    }
+
+    #[test]
+    fn test_instanced_mesh() {
+        let shared: Arc<dyn Shape> = Arc::new(
+            triangle()
+                .with_normals(vec![
+                    [0., 0., -1.],
+                    [-FRAC_1_SQRT_2, 0., -FRAC_1_SQRT_2],
+                    [FRAC_1_SQRT_2, 0., -FRAC_1_SQRT_2],
+                ])
+                .unwrap(),
+        );
+        let mut inst = Instance::new(shared).unwrap();
+        inst.set_transform(translation(10., 0., 0.));
+
+        let r = Ray::new(Point3::new(10., 0.5, -2.), Vector3::new(0., 0., 1.));
+        let i = hit(&inst.intersects(r)).unwrap();
+        let comps = prepare_computations(i, r, &inst);
+        assert_eq!(comps.normalv, Normal3::new(0., 0., -1.));
+    }
+}
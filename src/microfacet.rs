@@ -0,0 +1,149 @@
+use crate::colors::Color;
+use crate::materials::Material;
+use crate::vectors::{Normal3, Vector3};
+use std::f64::consts::PI;
+
+// below this the GGX lobe becomes a spike that a point light can never hit,
+// so the roughness gets clamped just like most engines do
+const MIN_ROUGHNESS: f64 = 0.045;
+
+pub fn distribution_ggx(n_dot_h: f64, alpha: f64) -> f64 {
+    let a2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (a2 - 1.) + 1.;
+    a2 / (PI * d * d)
+}
+
+pub fn visibility_smith(n_dot_v: f64, n_dot_l: f64, alpha: f64) -> f64 {
+    // height correlated smith term, already divided by 4 * n.l * n.v
+    let a2 = alpha * alpha;
+    let v = n_dot_l * (n_dot_v * n_dot_v * (1. - a2) + a2).sqrt();
+    let l = n_dot_v * (n_dot_l * n_dot_l * (1. - a2) + a2).sqrt();
+    0.5 / (v + l)
+}
+
+pub fn fresnel_schlick(cos_theta: f64, f0: Color) -> Color {
+    f0 + (Color::white() - f0) * (1. - cos_theta).powi(5)
+}
+
+pub fn brdf(
+    m: &Material,
+    normal: Normal3,
+    lightv: Vector3,
+    eyev: Vector3,
+) -> Color {
+    let n_dot_l = normal.dot(&lightv);
+    let n_dot_v = normal.dot(&eyev);
+
+    if n_dot_l <= 0. || n_dot_v <= 0. {
+        return Color::black();
+    }
+
+    let halfway = (lightv + eyev).normalize();
+    let n_dot_h = normal.dot(&halfway).max(0.);
+    let v_dot_h = eyev.dot(&halfway).max(0.);
+
+    let roughness = m.roughness.clamp(MIN_ROUGHNESS, 1.);
+    let alpha = roughness * roughness;
+
+    // metals have no diffuse part and tint their reflections
+    let dielectric = Color::new(m.fresnel, m.fresnel, m.fresnel);
+    let f0 = dielectric * (1. - m.metallic) + m.color * m.metallic;
+    let fresnel = fresnel_schlick(v_dot_h, f0);
+
+    let specular = fresnel
+        * distribution_ggx(n_dot_h, alpha)
+        * visibility_smith(n_dot_v, n_dot_l, alpha);
+
+    // light reflected by the fresnel term on the way in or out can't be
+    // diffused as well, weighting both directions keeps this reciprocal
+    let transmitted = (Color::white() - fresnel_schlick(n_dot_l, f0))
+        * (Color::white() - fresnel_schlick(n_dot_v, f0));
+    let diffuse = transmitted * m.color * ((1. - m.metallic) / PI);
+
+    diffuse + specular
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::materials::Material;
+    use crate::microfacet::brdf;
+    use crate::vectors::{Normal3, Vector3};
+    use std::f64::consts::PI;
+
+    // integrates brdf * cos over the hemisphere above a +z normal, which is
+    // the fraction of energy coming from eyev that gets reflected at all
+    fn albedo(m: &Material, eyev: Vector3) -> Color {
+        let normal = Normal3::new(0., 0., 1.);
+        let theta_steps = 256;
+        let phi_steps = 256;
+        let d_theta = (PI / 2.) / theta_steps as f64;
+        let d_phi = (2. * PI) / phi_steps as f64;
+
+        let mut sum = Color::black();
+        for i in 0..theta_steps {
+            let theta = (i as f64 + 0.5) * d_theta;
+            for j in 0..phi_steps {
+                let phi = (j as f64 + 0.5) * d_phi;
+                let lightv = Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                );
+                let weight = theta.cos() * theta.sin() * d_theta * d_phi;
+                sum = sum + brdf(m, normal, lightv, eyev) * weight;
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn test_brdf_below_horizon() {
+        let m = Material::microfacet(Color::white(), 0., 0.5);
+        let normal = Normal3::new(0., 0., 1.);
+        let eyev = Vector3::new(0., 0., 1.);
+
+        let r = brdf(&m, normal, Vector3::new(0., 0., -1.), eyev);
+        assert_eq!(r, Color::black());
+    }
+
+    #[test]
+    fn test_brdf_reciprocity() {
+        let m = Material::microfacet(Color::new(0.8, 0.4, 0.2), 0.3, 0.4);
+        let normal = Normal3::new(0., 0., 1.);
+        let a = Vector3::new(0.3, 0.2, 0.9).normalize();
+        let b = Vector3::new(-0.6, 0.1, 0.5).normalize();
+
+        assert_eq!(brdf(&m, normal, a, b), brdf(&m, normal, b, a));
+    }
+
+    #[test]
+    fn test_energy_conservation() {
+        for metallic in [0., 1.] {
+            for roughness in [0.3, 0.6, 1.] {
+                let m =
+                    Material::microfacet(Color::white(), metallic, roughness);
+
+                for angle in [0., PI / 4., PI / 2.5] {
+                    let eyev = Vector3::new(angle.sin(), 0., angle.cos());
+                    let a = albedo(&m, eyev);
+
+                    assert!(a.max_component() <= 1.);
+                    assert!(a.max_component() > 0.25);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_metal_has_no_diffuse() {
+        let m = Material::microfacet(Color::white(), 1., 1.);
+        let dull = Material::microfacet(Color::black(), 1., 1.);
+        let normal = Normal3::new(0., 0., 1.);
+        let eyev = Vector3::new(0., 0., 1.);
+        let lightv = Vector3::new(0.6, 0., 0.8);
+
+        assert!(brdf(&m, normal, lightv, eyev).max_component() > 0.);
+        assert!(brdf(&dull, normal, lightv, eyev).max_component() < 0.01);
+    }
+}
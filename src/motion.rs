@@ -0,0 +1,186 @@
+use crate::matrix::M;
+use crate::quaternion::Quaternion;
+use crate::transformations::{scaling, translation};
+use crate::vectors::{Normal3, Point3, Vector3};
+
+// a transform taken apart into scale, then rotation, then translation.
+// Blending these separately keeps a spinning object the same size all the
+// way through, blending the matrices themselves would shrink it
+#[derive(Debug, Clone, Copy)]
+pub struct Decomposed {
+    pub translation: Vector3,
+    pub rotation: Quaternion,
+    pub scale: Vector3,
+}
+
+impl Decomposed {
+    // shearing can't be represented and gets lost
+    pub fn new(m: &M) -> Decomposed {
+        let translation = Vector3::new(m.get(0, 3), m.get(1, 3), m.get(2, 3));
+
+        let column =
+            |j: usize| Vector3::new(m.get(0, j), m.get(1, j), m.get(2, j));
+        let (x, y, z) = (column(0), column(1), column(2));
+
+        // a mirrored transform gets a negative x scale, whatever is left is
+        // a proper rotation
+        let flip = if x.cross(&y).dot(&z) < 0. { -1. } else { 1. };
+        let scale =
+            Vector3::new(x.magnitude() * flip, y.magnitude(), z.magnitude());
+
+        let rotation = Quaternion::from_matrix(
+            &(m * &scaling(1. / scale.x, 1. / scale.y, 1. / scale.z)),
+        );
+
+        Decomposed {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn interpolate(&self, other: &Decomposed, t: f64) -> Decomposed {
+        Decomposed {
+            translation: self.translation
+                + (other.translation - self.translation) * t,
+            rotation: self.rotation.slerp(&other.rotation, t),
+            scale: self.scale + (other.scale - self.scale) * t,
+        }
+    }
+
+    pub fn matrix(&self) -> M {
+        let t = self.translation;
+        let s = self.scale;
+        translation(t.x, t.y, t.z)
+            * self.rotation.matrix()
+            * scaling(s.x, s.y, s.z)
+    }
+
+    // the parts are easy to undo one by one, no need for a general inverse
+    pub fn inverse_matrix(&self) -> M {
+        let t = self.translation;
+        let s = self.scale;
+        scaling(1. / s.x, 1. / s.y, 1. / s.z)
+            * self.rotation.conjugate().matrix()
+            * translation(-t.x, -t.y, -t.z)
+    }
+
+    // the same as multiplying by inverse_matrix, but straight from the
+    // parts so a ray at any time can be moved without building a matrix
+    pub fn inverse_point(&self, p: Point3) -> Point3 {
+        let v = self.inverse_vector(p - Point3::origin() - self.translation);
+        Point3::origin() + v
+    }
+
+    pub fn inverse_vector(&self, v: Vector3) -> Vector3 {
+        let r = self.rotation.conjugate().rotate(v);
+        let s = self.scale;
+        Vector3::new(r.x / s.x, r.y / s.y, r.z / s.z)
+    }
+
+    // a normal in the object's space taken out to where the parts put it,
+    // the inverse transpose of rotation and scale is the rotation with the
+    // scale divided out. Not normalized
+    pub fn normal_to_world(&self, n: Normal3) -> Normal3 {
+        let s = self.scale;
+        let v = Vector3::new(n.x / s.x, n.y / s.y, n.z / s.z);
+        Normal3::from_vector(self.rotation.rotate(v))
+    }
+}
+
+// an object moving from one transform to another while the shutter is open,
+// time 0 is the start and time 1 the end
+#[derive(Debug, Clone, Copy)]
+pub struct Motion {
+    pub start: Decomposed,
+    pub end: Decomposed,
+}
+
+impl Motion {
+    pub fn new(start: &M, end: &M) -> Motion {
+        Motion {
+            start: Decomposed::new(start),
+            end: Decomposed::new(end),
+        }
+    }
+
+    pub fn at(&self, time: f64) -> Decomposed {
+        self.start.interpolate(&self.end, time.clamp(0., 1.))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::matrix::M;
+    use crate::motion::{Decomposed, Motion};
+    use crate::transformations::{
+        rotation_x, rotation_y, scaling, translation,
+    };
+    use crate::vectors::{Normal3, Point3, Vector3};
+    use std::f64::consts::PI;
+
+    fn transform() -> M {
+        translation(1., -2., 3.)
+            * rotation_x(0.7)
+            * rotation_y(-1.2)
+            * scaling(2., 0.5, 3.)
+    }
+
+    #[test]
+    fn test_decompose_round_trip() {
+        for m in [
+            M::ident(4),
+            transform(),
+            rotation_y(PI),
+            scaling(-1., 2., 2.),
+        ] {
+            assert_eq!(Decomposed::new(&m).matrix(), m);
+        }
+    }
+
+    #[test]
+    fn test_decomposed_inverse() {
+        let m = transform();
+        assert_eq!(Decomposed::new(&m).inverse_matrix(), m.inverse());
+
+        // undoing the parts one by one lands where the matrix does
+        let d = Decomposed::new(&m);
+        let inverse = m.inverse();
+        let p = Point3::new(0.3, -1.5, 2.);
+        let v = Vector3::new(-1., 0.25, 0.5);
+        assert_eq!(d.inverse_point(p), &inverse * p);
+        assert_eq!(d.inverse_vector(v), &inverse * v);
+        let n = Normal3::new(0.2, 0.9, -0.4);
+        assert_eq!(d.normal_to_world(n), n.transform(&inverse));
+    }
+
+    #[test]
+    fn test_motion_ends() {
+        let start = translation(0., 1., 0.) * scaling(2., 2., 2.);
+        let end = translation(5., 0., 0.) * rotation_y(PI / 2.);
+        let motion = Motion::new(&start, &end);
+
+        assert_eq!(motion.at(0.).matrix(), start);
+        assert_eq!(motion.at(1.).matrix(), end);
+        // times outside the shutter stay at the ends
+        assert_eq!(motion.at(2.).matrix(), end);
+    }
+
+    #[test]
+    fn test_motion_halfway() {
+        let motion = Motion::new(
+            &(translation(0., 0., 0.) * scaling(1., 1., 1.)),
+            &(translation(4., 0., -2.) * scaling(3., 3., 3.)),
+        );
+        assert_eq!(
+            motion.at(0.5).matrix(),
+            translation(2., 0., -1.) * scaling(2., 2., 2.)
+        );
+    }
+
+    #[test]
+    fn test_rotation_keeps_its_size() {
+        let motion = Motion::new(&M::ident(4), &rotation_y(PI / 2.));
+        assert_eq!(motion.at(0.5).matrix(), rotation_y(PI / 4.));
+    }
+}
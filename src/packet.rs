@@ -0,0 +1,246 @@
+use crate::bounds::BoundingBox;
+use crate::geometries::Shape;
+use crate::intersections::{Intersection, Intersections};
+use crate::matrix::M;
+use crate::ray::Ray;
+use crate::vectors::{Point3, Vector3};
+
+// widest packet there is, narrower ones leave the last lanes unused
+pub const LANES: usize = 8;
+
+// which lanes of a packet still take part
+pub type Mask = [bool; LANES];
+
+// closest hit found so far for every lane
+pub type Hits = [Option<Intersection>; LANES];
+
+// up to eight rays that go roughly the same way, like the camera rays of
+// neighbouring pixels. The packet goes down the bvh together and pays for
+// one node fetch and ordering decision for all its lanes, but the lanes
+// themselves are still worked through one after the other. There is no
+// SIMD here, the coordinates are only stored lane by lane so it could be
+// added later
+#[derive(Debug, Clone, Copy)]
+pub struct RayPacket {
+    pub width: usize,
+    pub origin: [[f64; LANES]; 3],
+    pub direction: [[f64; LANES]; 3],
+    pub time: [f64; LANES],
+}
+
+impl RayPacket {
+    pub fn new(rays: &[Ray]) -> Result<RayPacket, String> {
+        if rays.is_empty() || rays.len() > LANES {
+            return Err(format!(
+                "a packet takes 1 to {} rays, got {}",
+                LANES,
+                rays.len()
+            ));
+        }
+
+        let mut packet = RayPacket {
+            width: rays.len(),
+            origin: [[0.; LANES]; 3],
+            direction: [[0.; LANES]; 3],
+            time: [0.; LANES],
+        };
+        for (l, r) in rays.iter().enumerate() {
+            packet.set_lane(l, *r);
+        }
+        Ok(packet)
+    }
+
+    fn set_lane(&mut self, l: usize, r: Ray) {
+        let (o, d) = (r.origin, r.direction);
+        for (i, (oc, dc)) in
+            [(o.x, d.x), (o.y, d.y), (o.z, d.z)].iter().enumerate()
+        {
+            self.origin[i][l] = *oc;
+            self.direction[i][l] = *dc;
+        }
+        self.time[l] = r.time;
+    }
+
+    pub fn ray(&self, l: usize) -> Ray {
+        let o = &self.origin;
+        let d = &self.direction;
+        Ray::new(
+            Point3::new(o[0][l], o[1][l], o[2][l]),
+            Vector3::new(d[0][l], d[1][l], d[2][l]),
+        )
+        .at_time(self.time[l])
+    }
+
+    // every lane the packet has rays for
+    pub fn mask(&self) -> Mask {
+        let mut mask = [false; LANES];
+        mask[..self.width].iter_mut().for_each(|m| *m = true);
+        mask
+    }
+
+    pub fn transform(&self, m: &M) -> RayPacket {
+        let mut moved = *self;
+        for i in 0..3 {
+            for l in 0..LANES {
+                moved.origin[i][l] = m.get(i, 0) * self.origin[0][l]
+                    + m.get(i, 1) * self.origin[1][l]
+                    + m.get(i, 2) * self.origin[2][l]
+                    + m.get(i, 3);
+                moved.direction[i][l] = m.get(i, 0) * self.direction[0][l]
+                    + m.get(i, 1) * self.direction[1][l]
+                    + m.get(i, 2) * self.direction[2][l];
+            }
+        }
+        moved
+    }
+
+    // lanes in the mask that enter the box before their limit
+    pub fn hits_box(
+        &self,
+        b: &BoundingBox,
+        mask: Mask,
+        limit: &[f64; LANES],
+    ) -> Mask {
+        let min = [b.min.x, b.min.y, b.min.z];
+        let max = [b.max.x, b.max.y, b.max.z];
+        let mut near = [f64::NEG_INFINITY; LANES];
+        let mut far = [f64::INFINITY; LANES];
+
+        for i in 0..3 {
+            for l in 0..LANES {
+                let (t1, t2) = slab(
+                    self.origin[i][l],
+                    self.direction[i][l],
+                    min[i],
+                    max[i],
+                );
+                near[l] = near[l].max(t1);
+                far[l] = far[l].min(t2);
+            }
+        }
+
+        let mut hits = [false; LANES];
+        for l in 0..LANES {
+            hits[l] = mask[l]
+                && near[l] <= far[l]
+                && far[l] >= 0.
+                && near[l] <= limit[l];
+        }
+        hits
+    }
+}
+
+// same as the scalar slab test, a ray parallel to the planes is either
+// always or never between them
+fn slab(origin: f64, direction: f64, min: f64, max: f64) -> (f64, f64) {
+    if direction == 0. {
+        return if origin >= min && origin <= max {
+            (f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            (f64::INFINITY, f64::NEG_INFINITY)
+        };
+    }
+
+    let t1 = (min - origin) / direction;
+    let t2 = (max - origin) / direction;
+    (t1.min(t2), t1.max(t2))
+}
+
+pub fn count(mask: &Mask) -> usize {
+    mask.iter().filter(|&&m| m).count()
+}
+
+// lane l alone
+pub fn single(l: usize) -> Mask {
+    let mut mask = [false; LANES];
+    mask[l] = true;
+    mask
+}
+
+// keeps the hit if it's in front of the ray and closer than the lane's
+// best so far
+pub fn keep_closer(hits: &mut Hits, l: usize, i: Intersection) {
+    if i.t >= 0. && hits[l].is_none_or(|h| i.t < h.t) {
+        hits[l] = Some(i);
+    }
+}
+
+// one ray after the other, for shapes without a packet test of their own
+pub fn scalar_lanes<S: Shape + ?Sized>(
+    shape: &S,
+    packet: &RayPacket,
+    mask: Mask,
+    hits: &mut Hits,
+) {
+    let mut xs = Intersections::new();
+    for l in (0..LANES).filter(|&l| mask[l]) {
+        xs.clear();
+        shape.intersect_into(packet.ray(l), &mut xs);
+        if let Some(h) = xs.hit() {
+            keep_closer(hits, l, h);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bounds::BoundingBox;
+    use crate::packet::{count, RayPacket, LANES};
+    use crate::ray::Ray;
+    use crate::transformations::translation;
+    use crate::vectors::{Point3, Vector3};
+
+    fn fan(n: usize) -> Vec<Ray> {
+        (0..n)
+            .map(|i| {
+                Ray::new(
+                    Point3::new(i as f64 - 4., 0., -5.),
+                    Vector3::new(0., 0., 1.),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_packet_width() {
+        assert!(RayPacket::new(&[]).is_err());
+        assert!(RayPacket::new(&fan(9)).is_err());
+
+        let p = RayPacket::new(&fan(4)).unwrap();
+        assert_eq!(count(&p.mask()), 4);
+        assert_eq!(p.ray(2).origin, Point3::new(-2., 0., -5.));
+    }
+
+    #[test]
+    fn test_packet_transform() {
+        let rays = fan(8);
+        let m = translation(1., 2., 3.);
+        let p = RayPacket::new(&rays).unwrap().transform(&m);
+
+        for (l, r) in rays.iter().enumerate() {
+            let moved = r.transform(&m);
+            assert_eq!(p.ray(l).origin, moved.origin);
+            assert_eq!(p.ray(l).direction, moved.direction);
+        }
+    }
+
+    #[test]
+    fn test_packet_hits_box() {
+        let b = BoundingBox::new(
+            Point3::new(-1.5, -1., -1.),
+            Point3::new(1.5, 1., 1.),
+        );
+        let rays = fan(8);
+        let p = RayPacket::new(&rays).unwrap();
+        let hits = p.hits_box(&b, p.mask(), &[f64::INFINITY; LANES]);
+
+        for (l, r) in rays.iter().enumerate() {
+            assert_eq!(hits[l], b.intersects(*r));
+        }
+        assert_eq!(count(&hits), 3);
+
+        // nothing counts past the limit
+        let hits = p.hits_box(&b, p.mask(), &[3.; LANES]);
+        assert_eq!(count(&hits), 0);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::colors::Color;
+use crate::intersections::{prepare_computations, Computations};
+use crate::lights::direct_lighting;
+use crate::ray::Ray;
+use crate::sampling::cosine_hemisphere;
+use crate::utils::EPSILON;
+use crate::vectors::Vector3;
+use crate::world::World;
+
+// follows light around the scene bounce after bounce, so surfaces pick up
+// the color of their neighbours and emissive objects light up the rest
+#[derive(Debug, Clone, Copy)]
+pub struct PathTracer {
+    // paths traced for every one of the camera sampler's offsets, each
+    // through a different spot around the offset
+    pub samples_per_pixel: usize,
+    // no path ever gets longer than this
+    pub max_depth: usize,
+    // bounces before russian roulette can start ending paths
+    pub min_depth: usize,
+}
+
+impl PathTracer {
+    pub fn new(samples_per_pixel: usize) -> PathTracer {
+        PathTracer {
+            samples_per_pixel,
+            max_depth: 16,
+            min_depth: 3,
+        }
+    }
+
+    // light arriving along the ray, one random path per call
+    pub fn radiance(&self, world: &World, ray: Ray) -> Color {
+        let mut ray = ray;
+        let mut radiance = Color::black();
+        let mut throughput = Color::white();
+
+        for depth in 0..self.max_depth {
+            let i = match world.hit(ray) {
+                Some(i) => i,
+                None => {
+                    let background = world.background.color_in(ray.direction);
+                    return radiance + throughput * background;
+                }
+            };
+            let object = world.object(i.object_id).unwrap();
+            let comps = prepare_computations(i, ray, object);
+            let m = *object.material();
+
+            // lights that aren't objects can't be hit by a bounce, so they
+            // are only ever counted here and there is nothing to double up
+            radiance = radiance + throughput * m.emissive;
+
+            // the material's own weights pick what happens next, so every
+            // choice carries its full contribution
+            let u = rand::random::<f64>();
+            let next = if u < m.transparency {
+                refract(&comps, m.refractive_index)
+            } else if u < m.transparency + m.reflective {
+                Ray::new(
+                    comps.over_point,
+                    ray.direction.reflect(&comps.normalv),
+                )
+            } else {
+                radiance = radiance + throughput * next_event(world, &comps);
+
+                let direction = cosine_hemisphere(
+                    comps.normalv,
+                    rand::random::<f64>(),
+                    rand::random::<f64>(),
+                );
+                let cos = direction.dot(&comps.normalv);
+                if cos <= 0. {
+                    break;
+                }
+
+                // the pdf is cos / pi, same estimator as the environment
+                // lighting in the world
+                throughput = throughput
+                    * direct_lighting(
+                        m,
+                        Color::white(),
+                        direction,
+                        comps.eyev,
+                        comps.normalv,
+                    )
+                    / cos;
+                Ray::new(comps.over_point, direction)
+            };
+
+            if throughput.max_component() <= 0. {
+                break;
+            }
+
+            if depth + 1 >= self.min_depth {
+                let survive = throughput.max_component().min(0.95);
+                if rand::random::<f64>() >= survive {
+                    break;
+                }
+                throughput = throughput / survive;
+            }
+
+            ray = next.at_time(ray.time);
+        }
+
+        radiance
+    }
+}
+
+// light reaching the point straight from the world's light, the ambient
+// term is left out because the bounces take care of indirect light
+fn next_event(world: &World, comps: &Computations) -> Color {
+    match &world.light {
+        None => Color::black(),
+        Some(light) => {
+            let mut m = *comps.object.material();
+            m.ambient = 0.;
+
+            world.light_hit(light.as_ref(), comps, m)
+        }
+    }
+}
+
+// goes through the surface or bounces off it as often as schlick's
+// approximation of the fresnel term says
+fn refract(comps: &Computations, refractive_index: f64) -> Ray {
+    let n_ratio = if comps.inside {
+        refractive_index
+    } else {
+        1. / refractive_index
+    };
+    let cos_i = comps.eyev.dot(&comps.normalv);
+    let sin2_t = n_ratio.powi(2) * (1. - cos_i.powi(2));
+    let reflected =
+        Ray::new(comps.over_point, (-comps.eyev).reflect(&comps.normalv));
+
+    // total internal reflection
+    if sin2_t > 1. {
+        return reflected;
+    }
+
+    let cos_t = (1. - sin2_t).sqrt();
+    let r0 = ((1. - n_ratio) / (1. + n_ratio)).powi(2);
+    let cos = if n_ratio > 1. { cos_t } else { cos_i };
+    let reflectance = r0 + (1. - r0) * (1. - cos).powi(5);
+
+    if rand::random::<f64>() < reflectance {
+        return reflected;
+    }
+
+    let under_point = comps.point - comps.normalv * EPSILON;
+    let direction: Vector3 =
+        comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
+    Ray::new(under_point, direction.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::environment::Background;
+    use crate::geometries::Sphere;
+    use crate::path_tracer::PathTracer;
+    use crate::ray::Ray;
+    use crate::transformations::scaling;
+    use crate::vectors::{Point3, Vector3};
+    use crate::world::World;
+
+    fn ray() -> Ray {
+        Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.))
+    }
+
+    #[test]
+    fn test_miss_sees_background() {
+        let mut w = World::new();
+        w.background = Background::Color(Color::new(0.2, 0.4, 0.6));
+
+        let pt = PathTracer::new(1);
+        assert_eq!(pt.radiance(&w, ray()), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn test_next_event_estimation() {
+        let mut w = World::default_world();
+        w.truncate(1);
+
+        // a lone convex object only bounces light into the black
+        // background, so what remains is the direct light without ambient
+        let m = *w.objects()[0].material();
+        let expected = w.color_at(ray()) - m.color * m.ambient;
+
+        let pt = PathTracer::new(1);
+        for _ in 0..10 {
+            assert_eq!(pt.radiance(&w, ray()), expected);
+        }
+    }
+
+    #[test]
+    fn test_emissive_objects_light_the_scene() {
+        let mut w = World::new();
+
+        let mut ball = Sphere::new();
+        ball.material.ambient = 0.;
+        ball.material.specular = 0.;
+        ball.material.diffuse = 0.5;
+        w.add(ball);
+
+        // glowing room all around the ball, it reflects nothing itself
+        let mut room = Sphere::new();
+        room.set_transform(scaling(10., 10., 10.));
+        room.material.color = Color::black();
+        room.material.specular = 0.;
+        room.material.emissive = Color::new(1., 1., 1.);
+        w.add(room);
+
+        let pt = PathTracer::new(1);
+        for _ in 0..10 {
+            assert_eq!(pt.radiance(&w, ray()), Color::new(0.5, 0.5, 0.5));
+        }
+    }
+
+    #[test]
+    fn test_white_furnace() {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.material.ambient = 0.;
+        s.material.specular = 0.;
+        w.add(s);
+        w.background = Background::Color(Color::new(1., 1., 1.));
+
+        let pt = PathTracer::new(1);
+        assert_eq!(pt.radiance(&w, ray()), Color::new(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn test_mirror() {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.material.reflective = 1.;
+        w.add(s);
+        w.background = Background::Color(Color::new(0.2, 0.4, 0.6));
+
+        let pt = PathTracer::new(1);
+        assert_eq!(pt.radiance(&w, ray()), Color::new(0.2, 0.4, 0.6));
+    }
+
+    #[test]
+    fn test_glass_passes_light_through() {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.material.transparency = 1.;
+        s.material.refractive_index = 1.5;
+        w.add(s);
+        w.background = Background::Color(Color::new(0.2, 0.4, 0.6));
+
+        // whether it gets reflected or refracted the path ends up in the
+        // background without losing any energy
+        let mut pt = PathTracer::new(1);
+        pt.min_depth = pt.max_depth;
+        for _ in 0..10 {
+            assert_eq!(pt.radiance(&w, ray()), Color::new(0.2, 0.4, 0.6));
+        }
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let mut w = World::new();
+        w.add(Sphere::new());
+        w.background = Background::Color(Color::new(1., 1., 1.));
+
+        let mut pt = PathTracer::new(1);
+        pt.max_depth = 0;
+        assert_eq!(pt.radiance(&w, ray()), Color::black());
+    }
+}
@@ -0,0 +1,327 @@
+use crate::matrix::M;
+use crate::scalar::Scalar;
+use crate::utils::float_compare;
+use crate::vectors::Vector3;
+use std::ops::{Mul, Neg};
+
+// a rotation around an arbitrary axis. Unlike a chain of euler angles it
+// blends smoothly and never gets stuck in gimbal lock
+#[derive(Debug, Clone, Copy)]
+pub struct Quaternion<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+    pub w: T,
+}
+
+impl<T: Scalar> Quaternion<T> {
+    pub fn new(x: T, y: T, z: T, w: T) -> Self {
+        Quaternion { x, y, z, w }
+    }
+
+    pub fn identity() -> Self {
+        Quaternion::new(T::ZERO, T::ZERO, T::ZERO, T::ONE)
+    }
+
+    // counterclockwise when looking down the axis towards the origin, the
+    // same way rotation_x/y/z turn
+    pub fn from_axis_angle(axis: Vector3<T>, angle: T) -> Self {
+        let axis = axis.normalize();
+        let half = angle / T::from_f64(2.);
+        let s = half.sin();
+        Quaternion::new(axis.x * s, axis.y * s, axis.z * s, half.cos())
+    }
+
+    // only the upper 3x3 is read and it has to be a proper rotation, scale
+    // has to be divided out first
+    pub fn from_matrix(m: &M<T>) -> Self {
+        let r = |i: usize, j: usize| m.get(i, j);
+        let (one, two, four) = (T::ONE, T::from_f64(2.), T::from_f64(4.));
+        let trace = r(0, 0) + r(1, 1) + r(2, 2);
+
+        // pick the biggest component to divide by so it stays stable
+        let q = if trace > T::ZERO {
+            let s = (trace + one).sqrt() * two;
+            Quaternion::new(
+                (r(2, 1) - r(1, 2)) / s,
+                (r(0, 2) - r(2, 0)) / s,
+                (r(1, 0) - r(0, 1)) / s,
+                s / four,
+            )
+        } else if r(0, 0) > r(1, 1) && r(0, 0) > r(2, 2) {
+            let s = (one + r(0, 0) - r(1, 1) - r(2, 2)).sqrt() * two;
+            Quaternion::new(
+                s / four,
+                (r(0, 1) + r(1, 0)) / s,
+                (r(0, 2) + r(2, 0)) / s,
+                (r(2, 1) - r(1, 2)) / s,
+            )
+        } else if r(1, 1) > r(2, 2) {
+            let s = (one + r(1, 1) - r(0, 0) - r(2, 2)).sqrt() * two;
+            Quaternion::new(
+                (r(0, 1) + r(1, 0)) / s,
+                s / four,
+                (r(1, 2) + r(2, 1)) / s,
+                (r(0, 2) - r(2, 0)) / s,
+            )
+        } else {
+            let s = (one + r(2, 2) - r(0, 0) - r(1, 1)).sqrt() * two;
+            Quaternion::new(
+                (r(0, 2) + r(2, 0)) / s,
+                (r(1, 2) + r(2, 1)) / s,
+                s / four,
+                (r(1, 0) - r(0, 1)) / s,
+            )
+        };
+        q.normalize()
+    }
+
+    // turns +z towards forward and keeps +y as close to up as it can, for
+    // pointing an object somewhere. Up must not be parallel to forward
+    pub fn look_rotation(forward: Vector3<T>, up: Vector3<T>) -> Self {
+        let forward = forward.normalize();
+        let right = up.cross(&forward).normalize();
+        let true_up = forward.cross(&right);
+
+        let (o, i) = (T::ZERO, T::ONE);
+        let m = M::new(vec![
+            vec![right.x, true_up.x, forward.x, o],
+            vec![right.y, true_up.y, forward.y, o],
+            vec![right.z, true_up.z, forward.z, o],
+            vec![o, o, o, i],
+        ])
+        .unwrap();
+        Quaternion::from_matrix(&m)
+    }
+
+    pub fn matrix(&self) -> M<T> {
+        let Quaternion { x, y, z, w } = *self;
+        let (o, i, two) = (T::ZERO, T::ONE, T::from_f64(2.));
+        M::new(vec![
+            vec![
+                i - two * (y * y + z * z),
+                two * (x * y - z * w),
+                two * (x * z + y * w),
+                o,
+            ],
+            vec![
+                two * (x * y + z * w),
+                i - two * (x * x + z * z),
+                two * (y * z - x * w),
+                o,
+            ],
+            vec![
+                two * (x * z - y * w),
+                two * (y * z + x * w),
+                i - two * (x * x + y * y),
+                o,
+            ],
+            vec![o, o, o, i],
+        ])
+        .unwrap()
+    }
+
+    pub fn dot(&self, other: &Self) -> T {
+        self.x * other.x
+            + self.y * other.y
+            + self.z * other.z
+            + self.w * other.w
+    }
+
+    pub fn normalize(&self) -> Self {
+        let length = self.dot(self).sqrt();
+        Quaternion::new(
+            self.x / length,
+            self.y / length,
+            self.z / length,
+            self.w / length,
+        )
+    }
+
+    // the opposite rotation, as long as the quaternion is a unit one
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(-self.x, -self.y, -self.z, self.w)
+    }
+
+    pub fn rotate(&self, v: Vector3<T>) -> Vector3<T> {
+        let axis = Vector3::new(self.x, self.y, self.z);
+        let t = axis.cross(&v) * T::from_f64(2.);
+        v + t * self.w + axis.cross(&t)
+    }
+
+    // spherical interpolation, always the short way around
+    pub fn slerp(&self, other: &Self, t: T) -> Self {
+        let mut b = *other;
+        let mut cos = self.dot(&b);
+        if cos < T::ZERO {
+            b = -b;
+            cos = -cos;
+        }
+
+        let blend = |wa: T, wb: T| {
+            Quaternion::new(
+                self.x * wa + b.x * wb,
+                self.y * wa + b.y * wb,
+                self.z * wa + b.z * wb,
+                self.w * wa + b.w * wb,
+            )
+        };
+
+        // nearly the same rotation, a straight line is good enough
+        if cos > T::from_f64(0.9995) {
+            return blend(T::ONE - t, t).normalize();
+        }
+
+        let theta = cos.acos();
+        blend(
+            ((T::ONE - t) * theta).sin() / theta.sin(),
+            (t * theta).sin() / theta.sin(),
+        )
+    }
+}
+
+impl<T: Scalar> PartialEq for Quaternion<T> {
+    fn eq(&self, other: &Self) -> bool {
+        float_compare(self.x, other.x)
+            && float_compare(self.y, other.y)
+            && float_compare(self.z, other.z)
+            && float_compare(self.w, other.w)
+    }
+}
+
+// rotates by rhs first and then by self, just like multiplying the matrices
+impl<T: Scalar> Mul for Quaternion<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        let (a, b) = (self, rhs);
+        Quaternion::new(
+            a.w * b.x + a.x * b.w + a.y * b.z - a.z * b.y,
+            a.w * b.y - a.x * b.z + a.y * b.w + a.z * b.x,
+            a.w * b.z + a.x * b.y - a.y * b.x + a.z * b.w,
+            a.w * b.w - a.x * b.x - a.y * b.y - a.z * b.z,
+        )
+    }
+}
+
+// the same rotation as self
+impl<T: Scalar> Neg for Quaternion<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Quaternion::new(-self.x, -self.y, -self.z, -self.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    crate::scalar::for_each_precision! {
+        use crate::transformations::{rotation_x, rotation_y, rotation_z};
+        use crate::utils::float_compare;
+
+        type M = crate::matrix::M<Float>;
+        type Quaternion = crate::quaternion::Quaternion<Float>;
+        type Vector3 = crate::vectors::Vector3<Float>;
+
+        const PI: Float = std::f64::consts::PI as Float;
+
+        fn spin() -> Quaternion {
+            Quaternion::from_axis_angle(Vector3::new(1., -2., 0.5), 0.8)
+        }
+
+        #[test]
+        fn test_identity() {
+            assert_eq!(Quaternion::identity().matrix(), M::ident(4));
+            let v = Vector3::new(1., 2., 3.);
+            assert_eq!(Quaternion::identity().rotate(v), v);
+        }
+
+        #[test]
+        fn test_axis_angle_matches_euler() {
+            let x = Vector3::new(1., 0., 0.);
+            let y = Vector3::new(0., 1., 0.);
+            let z = Vector3::new(0., 0., 1.);
+
+            for r in [PI / 4., PI / 2., -1.3, PI] {
+                assert_eq!(
+                    Quaternion::from_axis_angle(x, r).matrix(),
+                    rotation_x(r)
+                );
+                assert_eq!(
+                    Quaternion::from_axis_angle(y, r).matrix(),
+                    rotation_y(r)
+                );
+                assert_eq!(
+                    Quaternion::from_axis_angle(z * 3., r).matrix(),
+                    rotation_z(r)
+                );
+            }
+        }
+
+        #[test]
+        fn test_matrix_round_trip() {
+            for q in [
+                Quaternion::identity(),
+                spin(),
+                Quaternion::from_axis_angle(Vector3::new(0., 1., 0.), PI),
+                Quaternion::from_axis_angle(Vector3::new(1., 1., 0.), 3.),
+            ] {
+                let m = q.matrix();
+                assert_eq!(Quaternion::from_matrix(&m).matrix(), m);
+            }
+        }
+
+        #[test]
+        fn test_rotate_matches_matrix() {
+            let q = spin();
+            let v = Vector3::new(-2., 0.5, 4.);
+            assert_eq!(q.rotate(v), q.matrix() * v);
+        }
+
+        #[test]
+        fn test_composition() {
+            let a = spin();
+            let b = Quaternion::from_axis_angle(Vector3::new(0., 1., 1.), -2.);
+            assert_eq!((a * b).matrix(), a.matrix() * b.matrix());
+            assert_eq!(a * a.conjugate(), Quaternion::identity());
+        }
+
+        #[test]
+        fn test_slerp() {
+            let y = Vector3::new(0., 1., 0.);
+            let a = Quaternion::identity();
+            let b = Quaternion::from_axis_angle(y, PI / 2.);
+
+            assert_eq!(a.slerp(&b, 0.), a);
+            assert_eq!(a.slerp(&b, 1.), b);
+            assert_eq!(
+                a.slerp(&b, 0.5),
+                Quaternion::from_axis_angle(y, PI / 4.)
+            );
+            // the same rotation with the sign flipped still goes the short
+            // way
+            assert_eq!(a.slerp(&-b, 0.5).matrix(), rotation_y(PI / 4.));
+        }
+
+        #[test]
+        fn test_look_rotation() {
+            let up = Vector3::new(0., 1., 0.);
+            let q = Quaternion::look_rotation(Vector3::new(1., 0., 0.), up);
+            assert_eq!(
+                q.rotate(Vector3::new(0., 0., 1.)),
+                Vector3::new(1., 0., 0.)
+            );
+            assert_eq!(q.rotate(up), up);
+
+            let forward = Vector3::new(1., -2., 3.);
+            let q = Quaternion::look_rotation(forward, up);
+            assert_eq!(
+                q.rotate(Vector3::new(0., 0., 1.)),
+                forward.normalize()
+            );
+            // +x stays level and +y leans towards up
+            assert!(float_compare(q.rotate(Vector3::new(1., 0., 0.)).y, 0.));
+            assert!(q.rotate(up).y > 0.);
+        }
+    }
+}
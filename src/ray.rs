@@ -1,66 +1,91 @@
 use crate::matrix::M;
-use crate::vectors::Tuple;
+use crate::scalar::Scalar;
+use crate::vectors::{Point3, Vector3};
 
 #[derive(Debug, Clone, Copy)]
-pub struct Ray {
-    pub origin: Tuple,
-    pub direction: Tuple,
+pub struct Ray<T = f64> {
+    pub origin: Point3<T>,
+    pub direction: Vector3<T>,
+    // moment within the shutter interval, moving objects are seen where
+    // they are at this time
+    pub time: T,
 }
 
-impl Ray {
-    pub fn new(origin: Tuple, direction: Tuple) -> Ray {
-        Ray { origin, direction }
+impl<T: Scalar> Ray<T> {
+    pub fn new(origin: Point3<T>, direction: Vector3<T>) -> Ray<T> {
+        Ray {
+            origin,
+            direction,
+            time: T::ZERO,
+        }
+    }
+
+    pub fn at_time(&self, time: T) -> Ray<T> {
+        Ray { time, ..*self }
     }
 
-    pub fn position(&self, t: f64) -> Tuple {
-        self.origin + t * self.direction
+    pub fn position(&self, t: T) -> Point3<T> {
+        self.origin + self.direction * t
     }
 
-    pub fn transform(&self, m: &M) -> Ray {
+    pub fn transform(&self, m: &M<T>) -> Ray<T> {
         Ray {
             origin: m * self.origin,
             direction: m * self.direction,
+            time: self.time,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::ray::Ray;
-    use crate::transformations::{scaling, translation};
-    use crate::utils::float_compare;
-    use crate::vectors::Tuple;
+    crate::scalar::for_each_precision! {
+        use crate::transformations::{scaling, translation};
 
-    #[test]
-    fn test_position() {
-        let r = Ray::new(Tuple::point(2., 3., 4.), Tuple::vector(1., 0., 0.));
+        type Ray = crate::ray::Ray<Float>;
+        type Point3 = crate::vectors::Point3<Float>;
+        type Vector3 = crate::vectors::Vector3<Float>;
 
-        assert_eq!(r.position(0.), Tuple::point(2., 3., 4.));
+        #[test]
+        fn test_position() {
+            let r =
+                Ray::new(Point3::new(2., 3., 4.), Vector3::new(1., 0., 0.));
 
-        assert_eq!(r.position(1.), Tuple::point(3., 3., 4.));
+            assert_eq!(r.position(0.), Point3::new(2., 3., 4.));
 
-        assert_eq!(r.position(-1.), Tuple::point(1., 3., 4.));
+            assert_eq!(r.position(1.), Point3::new(3., 3., 4.));
 
-        assert_eq!(r.position(2.5), Tuple::point(4.5, 3., 4.));
-    }
+            assert_eq!(r.position(-1.), Point3::new(1., 3., 4.));
+
+            assert_eq!(r.position(2.5), Point3::new(4.5, 3., 4.));
+        }
 
-    #[test]
-    fn test_translation() {
-        let r1 = Ray::new(Tuple::point(1., 2., 3.), Tuple::vector(0., 1., 0.));
+        #[test]
+        fn test_translation() {
+            let r1 =
+                Ray::new(Point3::new(1., 2., 3.), Vector3::new(0., 1., 0.));
 
-        let r1_result =
-            Ray::new(Tuple::point(4., 6., 8.), Tuple::vector(0., 1., 0.));
+            let r1_result =
+                Ray::new(Point3::new(4., 6., 8.), Vector3::new(0., 1., 0.));
 
-        let r1_t = r1.transform(&translation(5., 5., 5.));
-    }
+            let r1_t = r1.transform(&translation(3., 4., 5.));
+
+            assert_eq!(r1_t.origin, r1_result.origin);
+            assert_eq!(r1_t.direction, r1_result.direction);
+        }
+
+        #[test]
+        fn test_scaling() {
+            let r1 =
+                Ray::new(Point3::new(1., 2., 3.), Vector3::new(0., 1., 0.));
 
-    #[test]
-    fn test_scaling() {
-        let r1 = Ray::new(Tuple::point(1., 2., 3.), Tuple::vector(0., 1., 0.));
+            let r1_result =
+                Ray::new(Point3::new(2., 6., 12.), Vector3::new(0., 3., 0.));
 
-        let r1_result =
-            Ray::new(Tuple::point(2., 6., 12.), Tuple::vector(0., 3., 0.));
+            let r1_t = r1.transform(&scaling(2., 3., 4.));
 
-        let r1_t = r1.transform(&scaling(2., 3., 4.));
+            assert_eq!(r1_t.origin, r1_result.origin);
+            assert_eq!(r1_t.direction, r1_result.direction);
+        }
     }
 }
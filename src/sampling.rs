@@ -0,0 +1,153 @@
+use crate::vectors::{Normal3, Vector3};
+use std::f64::consts::PI;
+
+// two vectors perpendicular to the normal and to each other
+pub fn orthonormal_basis(normal: Normal3) -> (Vector3, Vector3) {
+    let helper = if normal.x.abs() > 0.9 {
+        Vector3::new(0., 1., 0.)
+    } else {
+        Vector3::new(1., 0., 0.)
+    };
+
+    let tangent = helper.cross(&normal).normalize();
+    let bitangent = normal.cross(&tangent);
+    (tangent, bitangent)
+}
+
+// maps two uniform numbers from [0, 1) to a direction above the surface,
+// directions close to the normal are picked more often (pdf = cos / pi)
+pub fn cosine_hemisphere(normal: Normal3, u1: f64, u2: f64) -> Vector3 {
+    let r = u1.sqrt();
+    let phi = 2. * PI * u2;
+    let (tangent, bitangent) = orthonormal_basis(normal);
+
+    tangent * (r * phi.cos())
+        + bitangent * (r * phi.sin())
+        + normal * (1. - u1).max(0.).sqrt()
+}
+
+// uniform point on the unit disk, shirley's concentric mapping keeps
+// neighbouring numbers close together on the disk
+pub fn concentric_disk(u1: f64, u2: f64) -> (f64, f64) {
+    let a = 2. * u1 - 1.;
+    let b = 2. * u2 - 1.;
+    if a == 0. && b == 0. {
+        return (0., 0.);
+    }
+
+    let (r, phi) = if a.abs() > b.abs() {
+        (a, PI / 4. * (b / a))
+    } else {
+        (b, PI / 2. - PI / 4. * (a / b))
+    };
+    (r * phi.cos(), r * phi.sin())
+}
+
+// uniform point on a regular polygon with its corners on the unit circle,
+// the first corner sits at the given rotation
+pub fn regular_polygon(
+    sides: usize,
+    rotation: f64,
+    u1: f64,
+    u2: f64,
+    u3: f64,
+) -> (f64, f64) {
+    // every side makes a triangle with the center, all of the same size
+    let side = ((u1 * sides as f64) as usize).min(sides - 1);
+    let corner = |k: usize| {
+        let angle = rotation + 2. * PI * k as f64 / sides as f64;
+        (angle.cos(), angle.sin())
+    };
+    let (ax, ay) = corner(side);
+    let (bx, by) = corner(side + 1);
+
+    // fold the unit square onto the triangle
+    let (mut s, mut t) = (u2, u3);
+    if s + t > 1. {
+        s = 1. - s;
+        t = 1. - t;
+    }
+    (ax * s + bx * t, ay * s + by * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sampling::{
+        concentric_disk, cosine_hemisphere, orthonormal_basis, regular_polygon,
+    };
+    use crate::utils::{float_compare, EPSILON};
+    use crate::vectors::{Normal3, Vector3};
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_orthonormal_basis() {
+        for n in [
+            Normal3::new(0., 1., 0.),
+            Normal3::new(1., 0., 0.),
+            Normal3::new(1., 2., -3.).normalize(),
+        ] {
+            let (t, b) = orthonormal_basis(n);
+            assert!(float_compare(t.dot(&n), 0.));
+            assert!(float_compare(b.dot(&n), 0.));
+            assert!(float_compare(t.dot(&b), 0.));
+            assert!(float_compare(t.magnitude(), 1.));
+            assert!(float_compare(b.magnitude(), 1.));
+        }
+    }
+
+    #[test]
+    fn test_cosine_hemisphere() {
+        let n = Normal3::new(0., 0., -1.);
+
+        assert_eq!(cosine_hemisphere(n, 0., 0.3), Vector3::from(n));
+
+        for i in 0..10 {
+            for j in 0..10 {
+                let d = cosine_hemisphere(n, i as f64 / 10., j as f64 / 10.);
+                assert!(float_compare(d.magnitude(), 1.));
+                assert!(d.dot(&n) > 0.);
+            }
+        }
+    }
+
+    #[test]
+    fn test_concentric_disk() {
+        assert_eq!(concentric_disk(0.5, 0.5), (0., 0.));
+
+        let (x, y) = concentric_disk(1., 0.5);
+        assert!(float_compare(x, 1.) && float_compare(y, 0.));
+
+        for i in 0..=10 {
+            for j in 0..=10 {
+                let (x, y) = concentric_disk(i as f64 / 10., j as f64 / 10.);
+                assert!(x * x + y * y <= 1. + EPSILON);
+            }
+        }
+    }
+
+    #[test]
+    fn test_regular_polygon() {
+        let sides = 6;
+        for i in 0..10 {
+            for j in 0..10 {
+                for k in 0..10 {
+                    let (x, y) = regular_polygon(
+                        sides,
+                        0.3,
+                        i as f64 / 10.,
+                        j as f64 / 10.,
+                        k as f64 / 10.,
+                    );
+
+                    // inside means at most the apothem along the normal of
+                    // the side it's facing
+                    let angle = y.atan2(x) - 0.3;
+                    let wedge = 2. * PI / sides as f64;
+                    let offset = angle.rem_euclid(wedge) - wedge / 2.;
+                    let distance = (x * x + y * y).sqrt() * offset.cos();
+                    assert!(distance <= (wedge / 2.).cos() + EPSILON);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign,
+};
+
+// the float the math types are built on. Rendering is always done in f64,
+// a mesh can keep its buffers in f32 to halve their memory and the math
+// types work in either where the precision is good enough
+pub trait Scalar:
+    Copy
+    + Default
+    + PartialOrd
+    + Debug
+    + Display
+    + Sum
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+{
+    const ZERO: Self;
+    const ONE: Self;
+    // how far apart two values can be and still count as equal, f32 can't
+    // get as close as f64
+    const TOLERANCE: Self;
+
+    fn from_f64(x: f64) -> Self;
+    fn to_f64(self) -> f64;
+
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn sin(self) -> Self;
+    fn cos(self) -> Self;
+    fn acos(self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn min(self, other: Self) -> Self;
+}
+
+macro_rules! scalar {
+    ($t:ty, $tolerance:expr) => {
+        impl Scalar for $t {
+            const ZERO: $t = 0.;
+            const ONE: $t = 1.;
+            const TOLERANCE: $t = $tolerance;
+
+            fn from_f64(x: f64) -> $t {
+                x as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn sqrt(self) -> $t {
+                <$t>::sqrt(self)
+            }
+
+            fn abs(self) -> $t {
+                <$t>::abs(self)
+            }
+
+            fn sin(self) -> $t {
+                <$t>::sin(self)
+            }
+
+            fn cos(self) -> $t {
+                <$t>::cos(self)
+            }
+
+            fn acos(self) -> $t {
+                <$t>::acos(self)
+            }
+
+            fn max(self, other: $t) -> $t {
+                <$t>::max(self, other)
+            }
+
+            fn min(self, other: $t) -> $t {
+                <$t>::min(self, other)
+            }
+        }
+    };
+}
+
+scalar!(f64, crate::utils::EPSILON);
+scalar!(f32, 0.001);
+
+// the tests given to it once for every precision, they get the scalar as
+// `Float`
+#[cfg(test)]
+macro_rules! for_each_precision {
+    ($($test:item)*) => {
+        mod double {
+            type Float = f64;
+            $($test)*
+        }
+
+        mod single {
+            type Float = f32;
+            $($test)*
+        }
+    };
+}
+
+#[cfg(test)]
+pub(crate) use for_each_precision;
+
+#[cfg(test)]
+mod tests {
+    use crate::scalar::Scalar;
+    use crate::utils::float_compare;
+
+    #[test]
+    fn test_tolerance_follows_precision() {
+        assert!(float_compare(1.0_f64, 1.00005));
+        assert!(!float_compare(1.0_f64, 1.0005));
+        assert!(float_compare(1.0_f32, 1.0005));
+        assert!(f32::TOLERANCE.to_f64() > f64::TOLERANCE);
+    }
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(f32::from_f64(0.25), 0.25_f32);
+        assert_eq!(0.25_f32.to_f64(), 0.25);
+        assert_eq!(Scalar::max(2.0_f32, 3.), 3.);
+    }
+}
@@ -1,15 +1,13 @@
-use crate::vectors::Tuple;
+use crate::vectors::{Point3, Vector3};
 use std::f64::consts::PI;
 
 use crate::canvas::Canvas;
 use crate::colors::Color;
-use crate::geometries::Sphere;
+use crate::geometries::{Shape, Sphere};
 use crate::intersections::hit;
 use crate::lights::{lighting, PointLight};
 use crate::ray::Ray;
-use crate::transformations::{
-    rotation_x, rotation_y, rotation_z, scaling, translation,
-};
+use crate::transformations::{rotation_z, scaling, translation};
 
 pub fn write_projectile_image() {
     let mut canvas = Canvas::new(1200, 800);
@@ -27,14 +25,14 @@ pub fn write_projectile_image() {
     canvas.write_ppm("./projectile.ppm");
 }
 
-pub fn projectile(max_x: f64) -> Vec<Tuple> {
-    let gravity = Tuple::vector(0.0, -0.587, 0.0);
-    let wind = Tuple::vector(-0.03, 0.0, 0.0);
-    let mut position = Tuple::point(0.0, 1.0, 0.0);
+pub fn projectile(max_x: f64) -> Vec<Point3> {
+    let gravity = Vector3::new(0.0, -0.587, 0.0);
+    let wind = Vector3::new(-0.03, 0.0, 0.0);
+    let mut position = Point3::new(0.0, 1.0, 0.0);
 
-    let mut velocity = Tuple::vector(1.0, 1.8, 0.0).normalize() * 30.0;
+    let mut velocity = Vector3::new(1.0, 1.8, 0.0).normalize() * 30.0;
 
-    let mut position_log: Vec<Tuple> = vec![];
+    let mut position_log: Vec<Point3> = vec![];
 
     while position.x < max_x {
         position_log.push(position);
@@ -50,10 +48,10 @@ pub fn clock() {
 
     let steps = 120;
 
-    let mut p = Tuple::vector(0., 300., 0.);
+    let mut p = Point3::new(0., 300., 0.);
     let d = rotation_z((2. * PI) / (steps as f64));
 
-    for i in 0..steps {
+    for _ in 0..steps {
         p = &d * p;
         canvas.write_point(p, Color::red());
     }
@@ -72,9 +70,9 @@ pub fn ball_above() {
             let xf = x as f64;
             let yf = y as f64;
 
-            let hit = hit(ball.intersects(Ray::new(
-                Tuple::point(xf, yf, 10.),
-                Tuple::vector(xf, yf, -10.),
+            let hit = hit(&ball.intersects(Ray::new(
+                Point3::new(xf, yf, 10.),
+                Vector3::new(xf, yf, -10.),
             )));
 
             match hit {
@@ -96,26 +94,26 @@ pub fn ball_lightning() {
 
     ball.set_transform(translation(800., 800., 0.) * scaling(500., 500., 0.1));
 
-    let eye_position = Tuple::point(100., 100., 15.);
-    let light = PointLight {
-        intensity: Color::new(1., 1., 1.),
-        position: Tuple::point(100., 100., -500.),
-    };
+    let eye_position = Point3::new(100., 100., 15.);
+    let light = PointLight::new(
+        Point3::new(100., 100., -500.),
+        Color::new(1., 1., 1.),
+    );
 
     for y in 0..canvas.height {
         for x in 0..canvas.width {
             let xf = x as f64;
             let yf = y as f64;
 
-            let ray = Tuple::vector(xf, yf, -10.).normalize();
+            let ray = Vector3::new(xf, yf, -10.).normalize();
 
-            let hit = hit(ball.intersects(Ray::new(eye_position, ray)));
+            let r = Ray::new(eye_position, ray);
+            let hit = hit(&ball.intersects(r));
 
             match hit {
                 None => {}
                 Some(i) => {
-                    let mut position = ray * i.t;
-                    position.w = 1.;
+                    let position = r.position(i.t);
 
                     let normal = ball.normal(position);
 
@@ -125,6 +123,7 @@ pub fn ball_lightning() {
                         position,
                         -ray,
                         normal,
+                        1.,
                     );
 
                     canvas.write(x, y, r)
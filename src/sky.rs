@@ -0,0 +1,283 @@
+use crate::colors::Color;
+use crate::lights::DirectionalLight;
+use crate::utils::EPSILON;
+use crate::vectors::Vector3;
+use std::f64::consts::PI;
+
+// analytic daylight sky from Preetham, Shirley and Smits, "A Practical
+// Analytic Model for Daylight". Turbidity goes from about 2 for a clear
+// sky to 10 or more for a hazy one.
+pub struct Sky {
+    pub elevation: f64,
+    // measured from -z towards +x
+    pub azimuth: f64,
+    pub turbidity: f64,
+    // the model gives luminance in kcd/m^2, this brings it down to the
+    // range the rest of the renderer works in
+    pub exposure: f64,
+    // how much of the horizon light the ground reflects back up
+    pub ground: Color,
+}
+
+// coefficients A to E of the perez distribution function
+type Perez = [f64; 5];
+
+impl Sky {
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Sky {
+        Sky {
+            elevation,
+            azimuth,
+            turbidity,
+            exposure: 0.1,
+            ground: Color::new(0.3, 0.3, 0.3),
+        }
+    }
+
+    pub fn sun_direction(&self) -> Vector3 {
+        Vector3::new(
+            self.elevation.cos() * self.azimuth.sin(),
+            self.elevation.sin(),
+            -self.elevation.cos() * self.azimuth.cos(),
+        )
+    }
+
+    // the sun matching the sky, reddened by the air it has to go through
+    pub fn sun(&self) -> DirectionalLight {
+        let zenith_deg = 90. - self.elevation.to_degrees().clamp(0., 90.);
+        let cos_zenith = zenith_deg.to_radians().cos();
+
+        // kasten and young's relative air mass, 1 when the sun is straight up
+        let air_mass = 1.
+            / (cos_zenith + 0.50572 * (96.07995 - zenith_deg).powf(-1.6364));
+
+        // rayleigh optical depth for red, green and blue plus some haze
+        let haze = 0.035 * self.turbidity;
+        let transmittance =
+            |rayleigh: f64| (-(rayleigh + haze) * air_mass).exp();
+
+        DirectionalLight::new(
+            -self.sun_direction(),
+            Color::new(
+                transmittance(0.046),
+                transmittance(0.108),
+                transmittance(0.265),
+            ),
+        )
+    }
+
+    fn luminance_coefficients(&self) -> Perez {
+        let t = self.turbidity;
+        [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ]
+    }
+
+    fn x_coefficients(&self) -> Perez {
+        let t = self.turbidity;
+        [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ]
+    }
+
+    fn y_coefficients(&self) -> Perez {
+        let t = self.turbidity;
+        [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ]
+    }
+
+    // luminance and chromaticity straight up
+    fn zenith(&self) -> (f64, f64, f64) {
+        let t = self.turbidity;
+        let theta_s = PI / 2. - self.elevation;
+
+        let chi = (4. / 9. - t / 120.) * (PI - 2. * theta_s);
+        let luminance =
+            (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let chromaticity = |m: [[f64; 4]; 3]| {
+            let sun = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.];
+            let turbidity = [t * t, t, 1.];
+
+            let mut sum = 0.;
+            for (i, row) in m.iter().enumerate() {
+                for (j, value) in row.iter().enumerate() {
+                    sum += turbidity[i] * value * sun[j];
+                }
+            }
+            sum
+        };
+
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+
+        (luminance, x, y)
+    }
+
+    pub fn color_in(&self, direction: Vector3) -> Color {
+        let d = direction.normalize();
+
+        // the model knows nothing about the ground, so below the horizon we
+        // just reflect whatever arrives right at the horizon
+        let below = d.y < 0.;
+        let view = if !below {
+            d
+        } else if d.x.abs() + d.z.abs() < EPSILON {
+            Vector3::new(1., 0., 0.)
+        } else {
+            Vector3::new(d.x, 0., d.z).normalize()
+        };
+
+        let theta = view.y.clamp(0., 1.).acos();
+        let theta_s = PI / 2. - self.elevation;
+        let gamma = view.dot(&self.sun_direction()).clamp(-1., 1.).acos();
+
+        let (zenith_y, zenith_x, zenith_yc) = self.zenith();
+        let relative =
+            |c: Perez| perez(c, theta, gamma) / perez(c, 0., theta_s);
+
+        let luminance = zenith_y * relative(self.luminance_coefficients());
+        let x = zenith_x * relative(self.x_coefficients());
+        let y = zenith_yc * relative(self.y_coefficients());
+
+        let color = xyy_to_rgb(x, y, luminance * self.exposure);
+        if below {
+            color * self.ground
+        } else {
+            color
+        }
+    }
+}
+
+fn perez(c: Perez, theta: f64, gamma: f64) -> f64 {
+    let cos_theta = theta.cos().max(0.01);
+
+    (1. + c[0] * (c[1] / cos_theta).exp())
+        * (1. + c[2] * (c[3] * gamma).exp() + c[4] * gamma.cos().powi(2))
+}
+
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0. {
+        return Color::black();
+    }
+
+    let cx = x / y * luminance;
+    let cz = (1. - x - y) / y * luminance;
+
+    // linear srgb primaries
+    let r = 3.2406 * cx - 1.5372 * luminance - 0.4986 * cz;
+    let g = -0.9689 * cx + 1.8758 * luminance + 0.0415 * cz;
+    let b = 0.0557 * cx - 0.2040 * luminance + 1.0570 * cz;
+
+    Color::new(r.max(0.), g.max(0.), b.max(0.))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::lights::Light;
+    use crate::sky::Sky;
+    use crate::vectors::{Point3, Vector3};
+    use std::f64::consts::PI;
+
+    fn red(c: Color) -> f64 {
+        c.dot(&Color::new(1., 0., 0.))
+    }
+
+    fn blue(c: Color) -> f64 {
+        c.dot(&Color::new(0., 0., 1.))
+    }
+
+    #[test]
+    fn test_sun_direction() {
+        let noon = Sky::new(PI / 2., 0., 2.);
+        assert_eq!(noon.sun_direction(), Vector3::new(0., 1., 0.));
+
+        let sunset = Sky::new(0., 0., 2.);
+        assert_eq!(sunset.sun_direction(), Vector3::new(0., 0., -1.));
+
+        let east = Sky::new(0., PI / 2., 2.);
+        assert_eq!(east.sun_direction(), Vector3::new(1., 0., 0.));
+    }
+
+    #[test]
+    fn test_sun_light() {
+        let sky = Sky::new(PI / 4., PI / 3., 3.);
+        let sun = sky.sun();
+
+        for p in [Point3::new(0., 0., 0.), Point3::new(50., -10., 3.)] {
+            assert_eq!(sun.direction_from(p), sky.sun_direction());
+        }
+    }
+
+    #[test]
+    fn test_sun_reddens_near_horizon() {
+        let high = Sky::new(PI / 3., 0., 3.).sun().intensity();
+        let low = Sky::new(0.05, 0., 3.).sun().intensity();
+
+        assert!(high.max_component() <= 1.);
+        assert!(low.max_component() < high.max_component());
+
+        assert!(red(low) / blue(low) > red(high) / blue(high));
+    }
+
+    #[test]
+    fn test_clear_sky_is_blue() {
+        let sky = Sky::new(PI / 3., 0., 2.);
+        let zenith = sky.color_in(Vector3::new(0., 1., 0.));
+
+        assert!(blue(zenith) > red(zenith));
+        assert!(zenith.max_component() > 0.1);
+        assert!(zenith.max_component() < 5.);
+    }
+
+    #[test]
+    fn test_sky_brighter_around_sun() {
+        let sky = Sky::new(PI / 6., 0., 3.);
+        let towards = sky.color_in(Vector3::new(0., 0.6, -1.));
+        let away = sky.color_in(Vector3::new(0., 0.6, 1.));
+
+        assert!(towards.magnitude() > away.magnitude());
+    }
+
+    #[test]
+    fn test_below_horizon() {
+        let sky = Sky::new(PI / 4., 0., 3.);
+
+        for d in [Vector3::new(0., -1., 0.), Vector3::new(1., -0.2, 0.)] {
+            let c = sky.color_in(d);
+            assert!(c.magnitude().is_finite());
+            assert!(c.max_component() >= 0.);
+        }
+    }
+
+    #[test]
+    fn test_haze_washes_out_the_sky() {
+        let clear =
+            Sky::new(PI / 3., 0., 2.).color_in(Vector3::new(0., 1., 0.));
+        let hazy =
+            Sky::new(PI / 3., 0., 8.).color_in(Vector3::new(0., 1., 0.));
+
+        assert!(blue(hazy) / red(hazy) < blue(clear) / red(clear));
+    }
+}
@@ -1,221 +1,322 @@
 use crate::matrix::M;
+use crate::quaternion::Quaternion;
+use crate::scalar::Scalar;
+use crate::vectors::{Point3, Vector3};
 
-pub fn translation(x: f64, y: f64, z: f64) -> M {
+pub fn translation<T: Scalar>(x: T, y: T, z: T) -> M<T> {
+    let (o, i) = (T::ZERO, T::ONE);
     M::new(vec![
-        vec![1.0, 0.0, 0.0, x],
-        vec![0.0, 1.0, 0.0, y],
-        vec![0.0, 0.0, 1.0, z],
-        vec![0.0, 0.0, 0.0, 1.0],
+        vec![i, o, o, x],
+        vec![o, i, o, y],
+        vec![o, o, i, z],
+        vec![o, o, o, i],
     ])
     .unwrap()
 }
 
-pub fn scaling(x: f64, y: f64, z: f64) -> M {
+pub fn scaling<T: Scalar>(x: T, y: T, z: T) -> M<T> {
+    let (o, i) = (T::ZERO, T::ONE);
     M::new(vec![
-        vec![x, 0.0, 0.0, 0.0],
-        vec![0.0, y, 0.0, 0.0],
-        vec![0.0, 0.0, z, 0.0],
-        vec![0.0, 0.0, 0.0, 1.0],
+        vec![x, o, o, o],
+        vec![o, y, o, o],
+        vec![o, o, z, o],
+        vec![o, o, o, i],
     ])
     .unwrap()
 }
 
-pub fn rotation_x(r: f64) -> M {
+pub fn rotation_x<T: Scalar>(r: T) -> M<T> {
+    let (o, i) = (T::ZERO, T::ONE);
     M::new(vec![
-        vec![1.0, 0.0, 0.0, 0.0],
-        vec![0.0, r.cos(), -r.sin(), 0.0],
-        vec![0.0, r.sin(), r.cos(), 0.0],
-        vec![0.0, 0.0, 0.0, 1.0],
+        vec![i, o, o, o],
+        vec![o, r.cos(), -r.sin(), o],
+        vec![o, r.sin(), r.cos(), o],
+        vec![o, o, o, i],
     ])
     .unwrap()
 }
 
-pub fn rotation_y(r: f64) -> M {
+pub fn rotation_y<T: Scalar>(r: T) -> M<T> {
+    let (o, i) = (T::ZERO, T::ONE);
     M::new(vec![
-        vec![r.cos(), 0.0, r.sin(), 0.0],
-        vec![0.0, 1.0, 0.0, 0.0],
-        vec![-r.sin(), 0.0, r.cos(), 0.0],
-        vec![0.0, 0.0, 0.0, 1.0],
+        vec![r.cos(), o, r.sin(), o],
+        vec![o, i, o, o],
+        vec![-r.sin(), o, r.cos(), o],
+        vec![o, o, o, i],
     ])
     .unwrap()
 }
 
-pub fn rotation_z(r: f64) -> M {
+pub fn rotation_z<T: Scalar>(r: T) -> M<T> {
+    let (o, i) = (T::ZERO, T::ONE);
     M::new(vec![
-        vec![r.cos(), -r.sin(), 0.0, 0.0],
-        vec![r.sin(), r.cos(), 0.0, 0.0],
-        vec![0.0, 0.0, 1.0, 0.0],
-        vec![0.0, 0.0, 0.0, 1.0],
+        vec![r.cos(), -r.sin(), o, o],
+        vec![r.sin(), r.cos(), o, o],
+        vec![o, o, i, o],
+        vec![o, o, o, i],
     ])
     .unwrap()
 }
 
-pub fn shearing(
-    x_y: f64,
-    x_z: f64,
-    y_x: f64,
-    y_z: f64,
-    z_x: f64,
-    z_y: f64,
-) -> M {
+// turns by r around any axis through the origin
+pub fn rotation<T: Scalar>(axis: Vector3<T>, r: T) -> M<T> {
+    Quaternion::from_axis_angle(axis, r).matrix()
+}
+
+pub fn shearing<T: Scalar>(
+    x_y: T,
+    x_z: T,
+    y_x: T,
+    y_z: T,
+    z_x: T,
+    z_y: T,
+) -> M<T> {
+    let (o, i) = (T::ZERO, T::ONE);
     M::new(vec![
-        vec![1.0, x_y, x_z, 0.0],
-        vec![y_x, 1.0, y_z, 0.0],
-        vec![z_x, z_y, 1.0, 0.0],
-        vec![0.0, 0.0, 0.0, 1.0],
+        vec![i, x_y, x_z, o],
+        vec![y_x, i, y_z, o],
+        vec![z_x, z_y, i, o],
+        vec![o, o, o, i],
     ])
     .unwrap()
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::transformations::{
-        rotation_x, rotation_y, rotation_z, scaling, shearing, translation,
-    };
-    use std::f64::consts::PI;
-
-    use crate::vectors::Tuple;
-
-    #[test]
-    fn test_translation_point() {
-        let point_a = Tuple::point(-3.0, 4.0, 5.0);
-        let point_b = Tuple::point(2.0, 1.0, 7.0);
-
-        assert_eq!(translation(5.0, -3.0, 2.0) * point_a, point_b)
-    }
-
-    #[test]
-    fn test_translation_inverse() {
-        let point_a = Tuple::point(-3.0, 4.0, 5.0);
-        let point_b = Tuple::point(2.0, 1.0, 7.0);
-
-        let t = translation(5.0, -3.0, 2.0);
-
-        let res = t.clone() * point_a;
-        assert_eq!(res, point_b);
-
-        assert_eq!(t.inverse() * res, point_a)
-    }
-
-    #[test]
-    fn test_translation() {
-        let point_a = Tuple::vector(-3.0, 4.0, 5.0);
-
-        assert_eq!(translation(5.0, -3.0, 2.0) * point_a, point_a)
-    }
-
-    #[test]
-    fn test_scaling_point() {
-        let point_a = Tuple::point(-4.0, 6.0, 8.0);
-        let point_b = Tuple::point(-8.0, 18.0, 32.0);
-
-        assert_eq!(scaling(2.0, 3.0, 4.0) * point_a, point_b)
-    }
-
-    #[test]
-    fn test_scaling_vector() {
-        let v_a = Tuple::vector(-4.0, 6.0, 8.0);
-        let v_b = Tuple::vector(-8.0, 18.0, 32.0);
-
-        assert_eq!(scaling(2.0, 3.0, 4.0) * v_a, v_b)
-    }
+// orients the world relative to an eye at `from` looking towards `to`
+pub fn view_transform<T: Scalar>(
+    from: Point3<T>,
+    to: Point3<T>,
+    up: Vector3<T>,
+) -> M<T> {
+    let forward = (to - from).normalize();
+    let left = forward.cross(&up.normalize());
+    let true_up = left.cross(&forward);
+
+    let (o, i) = (T::ZERO, T::ONE);
+    let orientation = M::new(vec![
+        vec![left.x, left.y, left.z, o],
+        vec![true_up.x, true_up.y, true_up.z, o],
+        vec![-forward.x, -forward.y, -forward.z, o],
+        vec![o, o, o, i],
+    ])
+    .unwrap();
 
-    #[test]
-    fn test_scaling_vector_inverse() {
-        let v_a = Tuple::vector(-4.0, 6.0, 8.0);
-        let v_b = Tuple::vector(-2.0, 2.0, 2.0);
+    orientation * translation(-from.x, -from.y, -from.z)
+}
 
-        assert_eq!(scaling(2.0, 3.0, 4.0).inverse() * v_a, v_b)
-    }
+#[cfg(test)]
+mod tests {
+    crate::scalar::for_each_precision! {
+        use crate::transformations::{
+            rotation, rotation_x, rotation_y, rotation_z, scaling, shearing,
+            translation, view_transform,
+        };
 
-    #[test]
-    fn test_reflection() {
-        let v_a = Tuple::vector(2.0, 3.0, 4.0);
-        let v_b = Tuple::vector(-2.0, 3.0, 4.0);
+        type M = crate::matrix::M<Float>;
+        type Point3 = crate::vectors::Point3<Float>;
+        type Vector3 = crate::vectors::Vector3<Float>;
 
-        assert_eq!(scaling(-1.0, 1.0, 1.0) * v_a, v_b)
-    }
+        const PI: Float = std::f64::consts::PI as Float;
 
-    #[test]
-    fn test_rotation_x() {
-        let p = Tuple::point(0.0, 1.0, 0.0);
+        #[test]
+        fn test_translation_point() {
+            let point_a = Point3::new(-3.0, 4.0, 5.0);
+            let point_b = Point3::new(2.0, 1.0, 7.0);
 
-        let half_q = rotation_x(PI / 4.0);
-        let full_q = rotation_x(PI / 2.0);
+            assert_eq!(translation(5.0, -3.0, 2.0) * point_a, point_b)
+        }
 
-        let sq2 = (2.0 as f64).sqrt();
+        #[test]
+        fn test_translation_inverse() {
+            let point_a = Point3::new(-3.0, 4.0, 5.0);
+            let point_b = Point3::new(2.0, 1.0, 7.0);
 
-        assert_eq!(half_q * p, Tuple::point(0.0, sq2 / 2.0, sq2 / 2.0));
+            let t = translation(5.0, -3.0, 2.0);
 
-        assert_eq!(full_q * p, Tuple::point(0.0, 0.0, 1.0));
-    }
+            let res = t.clone() * point_a;
+            assert_eq!(res, point_b);
 
-    #[test]
-    fn test_rotation_x_inv() {
-        let p = Tuple::point(0.0, 1.0, 0.0);
+            assert_eq!(t.inverse() * res, point_a)
+        }
 
-        let half_q = rotation_x(PI / 4.0);
-        let sq2 = (2.0 as f64).sqrt();
+        #[test]
+        fn test_translation() {
+            let point_a = Vector3::new(-3.0, 4.0, 5.0);
 
-        assert_eq!(
-            half_q.inverse() * p,
-            Tuple::point(0.0, sq2 / 2.0, -sq2 / 2.0)
-        );
-    }
+            assert_eq!(translation(5.0, -3.0, 2.0) * point_a, point_a)
+        }
 
-    #[test]
-    fn test_rotation_y() {
-        let p = Tuple::point(0.0, 0.0, 1.0);
+        #[test]
+        fn test_scaling_point() {
+            let point_a = Point3::new(-4.0, 6.0, 8.0);
+            let point_b = Point3::new(-8.0, 18.0, 32.0);
 
-        let half_q = rotation_y(PI / 4.0);
-        let full_q = rotation_y(PI / 2.0);
+            assert_eq!(scaling(2.0, 3.0, 4.0) * point_a, point_b)
+        }
 
-        let sq2 = (2.0 as f64).sqrt();
+        #[test]
+        fn test_scaling_vector() {
+            let v_a = Vector3::new(-4.0, 6.0, 8.0);
+            let v_b = Vector3::new(-8.0, 18.0, 32.0);
 
-        assert_eq!(half_q * p, Tuple::point(sq2 / 2.0, 0.0, sq2 / 2.0));
+            assert_eq!(scaling(2.0, 3.0, 4.0) * v_a, v_b)
+        }
 
-        assert_eq!(full_q * p, Tuple::point(1.0, 0.0, 0.0));
-    }
+        #[test]
+        fn test_scaling_vector_inverse() {
+            let v_a = Vector3::new(-4.0, 6.0, 8.0);
+            let v_b = Vector3::new(-2.0, 2.0, 2.0);
 
-    #[test]
-    fn test_rotation_z() {
-        let p = Tuple::point(0.0, 1.0, 0.0);
+            assert_eq!(scaling(2.0, 3.0, 4.0).inverse() * v_a, v_b)
+        }
 
-        let half_q = rotation_z(PI / 4.0);
-        let full_q = rotation_z(PI / 2.0);
+        #[test]
+        fn test_reflection() {
+            let v_a = Vector3::new(2.0, 3.0, 4.0);
+            let v_b = Vector3::new(-2.0, 3.0, 4.0);
 
-        let sq2 = (2.0 as f64).sqrt();
+            assert_eq!(scaling(-1.0, 1.0, 1.0) * v_a, v_b)
+        }
 
-        assert_eq!(half_q * p, Tuple::point(-sq2 / 2.0, sq2 / 2.0, 0.0));
+        #[test]
+        fn test_rotation_x() {
+            let p = Point3::new(0.0, 1.0, 0.0);
 
-        assert_eq!(full_q * p, Tuple::point(-1.0, 0.0, 0.0));
-    }
+            let half_q = rotation_x(PI / 4.0);
+            let full_q = rotation_x(PI / 2.0);
 
-    #[test]
-    fn test_shearing_x_y() {
-        let v_a = Tuple::vector(2.0, 3.0, 4.0);
-        let v_b = Tuple::vector(5.0, 3.0, 4.0);
+            let sq2 = Float::sqrt(2.0);
 
-        assert_eq!(shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0) * v_a, v_b)
-    }
+            assert_eq!(half_q * p, Point3::new(0.0, sq2 / 2.0, sq2 / 2.0));
 
-    #[test]
-    fn test_sequence() {
-        let p = Tuple::point(1.0, 0.0, 1.0);
-        let m_a = rotation_x(PI / 2.0);
-        let m_b = scaling(5., 5., 5.);
-        let m_c = translation(10., 5., 7.);
+            assert_eq!(full_q * p, Point3::new(0.0, 0.0, 1.0));
+        }
 
-        let p2 = m_a.clone() * p;
-        assert_eq!(p2, Tuple::point(1., -1., 0.));
+        #[test]
+        fn test_rotation_x_inv() {
+            let p = Point3::new(0.0, 1.0, 0.0);
 
-        let p3 = m_b.clone() * p2;
-        assert_eq!(p3, Tuple::point(5., -5., 0.));
+            let half_q = rotation_x(PI / 4.0);
+            let sq2 = Float::sqrt(2.0);
 
-        let p4 = m_c.clone() * p3;
-        assert_eq!(p4, Tuple::point(15., 0., 7.));
+            assert_eq!(
+                half_q.inverse() * p,
+                Point3::new(0.0, sq2 / 2.0, -sq2 / 2.0)
+            );
+        }
 
-        // should be same as:
-        assert_eq!(m_c * m_b * m_a * p, p4);
+        #[test]
+        fn test_rotation_y() {
+            let p = Point3::new(0.0, 0.0, 1.0);
+
+            let half_q = rotation_y(PI / 4.0);
+            let full_q = rotation_y(PI / 2.0);
+
+            let sq2 = Float::sqrt(2.0);
+
+            assert_eq!(half_q * p, Point3::new(sq2 / 2.0, 0.0, sq2 / 2.0));
+
+            assert_eq!(full_q * p, Point3::new(1.0, 0.0, 0.0));
+        }
+
+        #[test]
+        fn test_rotation_z() {
+            let p = Point3::new(0.0, 1.0, 0.0);
+
+            let half_q = rotation_z(PI / 4.0);
+            let full_q = rotation_z(PI / 2.0);
+
+            let sq2 = Float::sqrt(2.0);
+
+            assert_eq!(half_q * p, Point3::new(-sq2 / 2.0, sq2 / 2.0, 0.0));
+
+            assert_eq!(full_q * p, Point3::new(-1.0, 0.0, 0.0));
+        }
+
+        #[test]
+        fn test_rotation_any_axis() {
+            let x = Vector3::new(1., 0., 0.);
+            assert_eq!(rotation(x, PI / 3.), rotation_x(PI / 3.));
+
+            // a third of a turn around the diagonal swaps the axes around
+            let diagonal = Vector3::new(1., 1., 1.);
+            let r = rotation(diagonal, 2. * PI / 3.);
+            assert_eq!(r.clone() * x, Vector3::new(0., 1., 0.));
+            assert_eq!(r * Point3::new(0., 0., 2.), Point3::new(2., 0., 0.));
+        }
+
+        #[test]
+        fn test_shearing_x_y() {
+            let v_a = Vector3::new(2.0, 3.0, 4.0);
+            let v_b = Vector3::new(5.0, 3.0, 4.0);
+
+            assert_eq!(shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0) * v_a, v_b)
+        }
+
+        #[test]
+        fn test_sequence() {
+            let p = Point3::new(1.0, 0.0, 1.0);
+            let m_a = rotation_x(PI / 2.0);
+            let m_b = scaling(5., 5., 5.);
+            let m_c = translation(10., 5., 7.);
+
+            let p2 = m_a.clone() * p;
+            assert_eq!(p2, Point3::new(1., -1., 0.));
+
+            let p3 = m_b.clone() * p2;
+            assert_eq!(p3, Point3::new(5., -5., 0.));
+
+            let p4 = m_c.clone() * p3;
+            assert_eq!(p4, Point3::new(15., 0., 7.));
+
+            // should be same as:
+            assert_eq!(m_c * m_b * m_a * p, p4);
+        }
+
+        #[test]
+        fn test_view_transform_default() {
+            let t = view_transform(
+                Point3::new(0., 0., 0.),
+                Point3::new(0., 0., -1.),
+                Vector3::new(0., 1., 0.),
+            );
+            assert_eq!(t, M::ident(4));
+        }
+
+        #[test]
+        fn test_view_transform_positive_z() {
+            let t = view_transform(
+                Point3::new(0., 0., 0.),
+                Point3::new(0., 0., 1.),
+                Vector3::new(0., 1., 0.),
+            );
+            assert_eq!(t, scaling(-1., 1., -1.));
+        }
+
+        #[test]
+        fn test_view_transform_moves_world() {
+            let t = view_transform(
+                Point3::new(0., 0., 8.),
+                Point3::new(0., 0., 0.),
+                Vector3::new(0., 1., 0.),
+            );
+            assert_eq!(t, translation(0., 0., -8.));
+        }
+
+        #[test]
+        fn test_view_transform_arbitrary() {
+            let t = view_transform(
+                Point3::new(1., 3., 2.),
+                Point3::new(4., -2., 8.),
+                Vector3::new(1., 1., 0.),
+            );
+            let expected = M::new(vec![
+                vec![-0.50709, 0.50709, 0.67612, -2.36643],
+                vec![0.76772, 0.60609, 0.12122, -2.82843],
+                vec![-0.35857, 0.59761, -0.71714, 0.00000],
+                vec![0.00000, 0.00000, 0.00000, 1.00000],
+            ])
+            .unwrap();
+            assert_eq!(t, expected);
+        }
     }
 }
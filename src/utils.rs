@@ -1,3 +1,7 @@
-pub fn float_compare(a: f64, b: f64) -> bool {
-    (a - b).abs() < 0.0001
+use crate::scalar::Scalar;
+
+pub const EPSILON: f64 = 0.0001;
+
+pub fn float_compare<T: Scalar>(a: T, b: T) -> bool {
+    (a - b).abs() < T::TOLERANCE
 }
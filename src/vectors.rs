@@ -1,332 +1,428 @@
+use crate::matrix::M;
+use crate::scalar::Scalar;
 use crate::utils::float_compare;
-use std::fmt;
-use std::ops::{Add, Div, Mul, Neg, Sub}; // Import `fmt`
+use std::ops::{Add, Div, Mul, Neg, Sub};
 
+// a position in space. Points can be moved by vectors and the difference
+// of two of them is a vector, but they can't be added or scaled
 #[derive(Debug, Clone, Copy)]
-pub struct Tuple {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-    pub w: f64,
+pub struct Point3<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
-impl fmt::Display for Tuple {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "({:.2}, {:.2}, {:.2}, {:.2})",
-            self.x, self.y, self.z, self.w
-        )
-    }
+// a direction with a length
+#[derive(Debug, Clone, Copy)]
+pub struct Vector3<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
 }
 
-impl Tuple {
-    pub fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
-        Self {
-            x: x,
-            y: y,
-            z: z,
-            w: w,
-        }
-    }
+// the direction a surface faces. Moving the surface moves its normals by
+// the inverse transpose of the transform, not the transform itself
+#[derive(Debug, Clone, Copy)]
+pub struct Normal3<T = f64> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
 
-    pub fn point(x: f64, y: f64, z: f64) -> Self {
-        Self {
-            x: x,
-            y: y,
-            z: z,
-            w: 1.0,
-        }
+impl<T: Scalar> Point3<T> {
+    pub fn new(x: T, y: T, z: T) -> Self {
+        Self { x, y, z }
     }
-    pub fn vector(x: f64, y: f64, z: f64) -> Self {
-        Self {
-            x: x,
-            y: y,
-            z: z,
-            w: 0.0,
-        }
+
+    pub fn origin() -> Self {
+        Self::new(T::ZERO, T::ZERO, T::ZERO)
     }
+}
 
-    pub fn magnitude(&self) -> f64 {
-        (self.x.powi(2) + self.y.powi(2) + self.z.powi(2)).sqrt()
+impl<T: Scalar> Vector3<T> {
+    pub fn new(x: T, y: T, z: T) -> Self {
+        Self { x, y, z }
     }
 
-    pub fn normalize(&self) -> Self {
-        let m = self.magnitude();
-        Self {
-            x: self.x / m,
-            y: self.y / m,
-            z: self.z / m,
-            w: self.w,
-        }
+    pub fn zero() -> Self {
+        Self::new(T::ZERO, T::ZERO, T::ZERO)
     }
 
-    pub fn dot(&self, rhs: &Self) -> f64 {
-        // if self.w == 1.0 || rhs.w == 1.0 {
-        //     panic!("Cant dot point to point!")
-        // }
+    pub fn magnitude(&self) -> T {
+        self.dot(self).sqrt()
+    }
 
-        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z + self.w * rhs.w
+    pub fn normalize(&self) -> Self {
+        *self / self.magnitude()
     }
 
-    fn cross(&self, rhs: &Self) -> Self {
-        if self.w == 1.0 && rhs.w == 1.0 {
-            panic!("Cant add point to point!")
-        }
+    // vectors and normals go together either way
+    pub fn dot<D: Into<Vector3<T>> + Copy>(&self, rhs: &D) -> T {
+        let rhs: Vector3<T> = (*rhs).into();
+        self.x * rhs.x + self.y * rhs.y + self.z * rhs.z
+    }
 
-        Tuple::new(
+    pub fn cross<D: Into<Vector3<T>> + Copy>(&self, rhs: &D) -> Self {
+        let rhs: Vector3<T> = (*rhs).into();
+        Vector3::new(
             self.y * rhs.z - self.z * rhs.y,
             self.z * rhs.x - self.x * rhs.z,
             self.x * rhs.y - self.y * rhs.x,
-            0.0,
         )
     }
 
-    pub fn reflect(&self, normal: &Self) -> Tuple {
-        self - &((*normal) * 2. * self.dot(normal))
+    pub fn reflect<D: Into<Vector3<T>> + Copy>(&self, normal: &D) -> Self {
+        let normal: Vector3<T> = (*normal).into();
+        *self - normal * T::from_f64(2.) * self.dot(&normal)
+    }
+}
+
+impl<T: Scalar> Normal3<T> {
+    pub fn new(x: T, y: T, z: T) -> Self {
+        Self { x, y, z }
+    }
+
+    // only for a vector already known to stand at a right angle to the
+    // surface, any other one doesn't transform like a normal
+    pub fn from_vector(v: Vector3<T>) -> Self {
+        Normal3::new(v.x, v.y, v.z)
+    }
+
+    pub fn normalize(&self) -> Self {
+        Normal3::from_vector(Vector3::from(*self).normalize())
+    }
+
+    pub fn dot<D: Into<Vector3<T>> + Copy>(&self, rhs: &D) -> T {
+        Vector3::from(*self).dot(rhs)
+    }
+
+    pub fn cross<D: Into<Vector3<T>> + Copy>(&self, rhs: &D) -> Vector3<T> {
+        Vector3::from(*self).cross(rhs)
+    }
+
+    // the normal of the surface after it went through the transform whose
+    // inverse is given, not normalized
+    pub fn transform(&self, inverse: &M<T>) -> Self {
+        let column = |j: usize| {
+            inverse.get(0, j) * self.x
+                + inverse.get(1, j) * self.y
+                + inverse.get(2, j) * self.z
+        };
+        Normal3::new(column(0), column(1), column(2))
+    }
+}
+
+impl<T: Scalar> From<Normal3<T>> for Vector3<T> {
+    fn from(n: Normal3<T>) -> Self {
+        Vector3::new(n.x, n.y, n.z)
     }
 }
 
-impl PartialEq for Tuple {
+impl<T: Scalar> PartialEq for Point3<T> {
     fn eq(&self, other: &Self) -> bool {
         float_compare(self.x, other.x)
             && float_compare(self.y, other.y)
             && float_compare(self.z, other.z)
-            && float_compare(self.w, other.w)
     }
 }
 
-impl Add for Tuple {
-    type Output = Tuple;
-
-    fn add(self, rhs: Self) -> Self::Output {
-        if self.w == 1.0 && rhs.w == 1.0 {
-            panic!("Cant add point to point!")
-        }
+impl<T: Scalar> PartialEq for Vector3<T> {
+    fn eq(&self, other: &Self) -> bool {
+        float_compare(self.x, other.x)
+            && float_compare(self.y, other.y)
+            && float_compare(self.z, other.z)
+    }
+}
 
-        Tuple::new(
-            self.x + rhs.x,
-            self.y + rhs.y,
-            self.z + rhs.z,
-            self.w + rhs.w,
-        )
+impl<T: Scalar> PartialEq for Normal3<T> {
+    fn eq(&self, other: &Self) -> bool {
+        float_compare(self.x, other.x)
+            && float_compare(self.y, other.y)
+            && float_compare(self.z, other.z)
     }
 }
 
-impl Sub for Tuple {
-    type Output = Tuple;
+impl<T: Scalar> Sub for Point3<T> {
+    type Output = Vector3<T>;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        // if self.w == 1.0 && rhs.w == 1.0 {
-        //     panic!("Cant add point to point!")
-        // }
-
-        Tuple::new(
-            self.x - rhs.x,
-            self.y - rhs.y,
-            self.z - rhs.z,
-            self.w - rhs.w,
-        )
+        Vector3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
     }
 }
 
-impl Sub for &Tuple {
-    type Output = Tuple;
+impl<T: Scalar> Add<Vector3<T>> for Point3<T> {
+    type Output = Point3<T>;
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        Tuple::new(
-            self.x - rhs.x,
-            self.y - rhs.y,
-            self.z - rhs.z,
-            self.w - rhs.w,
-        )
+    fn add(self, rhs: Vector3<T>) -> Self::Output {
+        Point3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
     }
 }
 
-impl Neg for Tuple {
-    type Output = Tuple;
+impl<T: Scalar> Sub<Vector3<T>> for Point3<T> {
+    type Output = Point3<T>;
 
-    fn neg(self) -> Self::Output {
-        Tuple::new(-self.x, -self.y, -self.z, self.w)
+    fn sub(self, rhs: Vector3<T>) -> Self::Output {
+        Point3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
     }
 }
 
-impl Mul<Tuple> for f64 {
-    type Output = Tuple;
+impl<T: Scalar> Add for Vector3<T> {
+    type Output = Vector3<T>;
 
-    fn mul(self, rhs: Tuple) -> Self::Output {
-        if rhs.w == 1.0 {
-            panic!("Cant use scalar multiplication on a point!!")
-        }
+    fn add(self, rhs: Self) -> Self::Output {
+        Vector3::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
 
-        Tuple::new(self * rhs.x, self * rhs.y, self * rhs.z, rhs.w)
+impl<T: Scalar> Sub for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Vector3::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
     }
 }
 
-impl Mul<f64> for Tuple {
-    type Output = Tuple;
+impl<T: Scalar> Neg for Vector3<T> {
+    type Output = Vector3<T>;
 
-    fn mul(self, rhs: f64) -> Self::Output {
-        if self.w == 1.0 {
-            panic!("Cant use scalar multiplication on a point!!")
-        }
+    fn neg(self) -> Self::Output {
+        Vector3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl<T: Scalar> Mul<T> for Vector3<T> {
+    type Output = Vector3<T>;
 
-        Tuple::new(self.x * rhs, self.y * rhs, self.z * rhs, self.w)
+    fn mul(self, rhs: T) -> Self::Output {
+        Vector3::new(self.x * rhs, self.y * rhs, self.z * rhs)
     }
 }
 
-impl Div<f64> for Tuple {
-    type Output = Tuple;
+// a generic impl can't have the scalar on the left, so every precision
+// gets its own
+macro_rules! scalar_times {
+    ($t:ty) => {
+        impl Mul<Vector3<$t>> for $t {
+            type Output = Vector3<$t>;
 
-    fn div(self, rhs: f64) -> Self::Output {
-        if self.w == 1.0 {
-            panic!("Cant use scalar multiplication on a point!!")
+            fn mul(self, rhs: Vector3<$t>) -> Self::Output {
+                rhs * self
+            }
         }
+    };
+}
+
+scalar_times!(f64);
+scalar_times!(f32);
 
-        Tuple::new(self.x / rhs, self.y / rhs, self.z / rhs, self.w)
+impl<T: Scalar> Div<T> for Vector3<T> {
+    type Output = Vector3<T>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        Vector3::new(self.x / rhs, self.y / rhs, self.z / rhs)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::utils::float_compare;
-    use crate::vectors::Tuple;
-
-    #[test]
-    fn test_point() {
-        let point = Tuple::point(1.0, 2.0, 3.0);
-        assert_eq!(point.x, 1.0);
-        assert_eq!(point.y, 2.0);
-        assert_eq!(point.w, 1.0);
+impl<T: Scalar> Neg for Normal3<T> {
+    type Output = Normal3<T>;
+
+    fn neg(self) -> Self::Output {
+        Normal3::new(-self.x, -self.y, -self.z)
     }
+}
+
+// a normal scaled is an offset along it, like the nudge off a surface
+impl<T: Scalar> Mul<T> for Normal3<T> {
+    type Output = Vector3<T>;
 
-    #[test]
-    fn test_compare() {
-        let point_a = Tuple::point(1.0, 2.0, 3.0);
-        let point_b = Tuple::point(1.0, 2.0, 3.0);
-        assert_eq!(point_a, point_b);
+    fn mul(self, rhs: T) -> Self::Output {
+        Vector3::from(self) * rhs
     }
+}
 
-    #[test]
-    fn test_add_vectors() {
-        let vector_a = Tuple::vector(1.0, 2.0, 3.0);
-        let vector_b = Tuple::vector(2.0, 3.0, 5.0);
+#[cfg(test)]
+mod tests {
+    crate::scalar::for_each_precision! {
+        use crate::transformations::{rotation_z, scaling, translation};
+        use crate::utils::float_compare;
+
+        type Normal3 = crate::vectors::Normal3<Float>;
+        type Point3 = crate::vectors::Point3<Float>;
+        type Vector3 = crate::vectors::Vector3<Float>;
+
+        #[test]
+        fn test_point() {
+            let point = Point3::new(1.0, 2.0, 3.0);
+            assert_eq!(point.x, 1.0);
+            assert_eq!(point.y, 2.0);
+            assert_eq!(point.z, 3.0);
+        }
 
-        let vector_sum = Tuple::vector(3.0, 5.0, 8.0);
+        #[test]
+        fn test_compare() {
+            let point_a = Point3::new(1.0, 2.0, 3.0);
+            let point_b = Point3::new(1.0, 2.0, 3.0);
+            assert_eq!(point_a, point_b);
+        }
 
-        assert_eq!(vector_a + vector_b, vector_sum);
-    }
+        #[test]
+        fn test_add_vectors() {
+            let vector_a = Vector3::new(1.0, 2.0, 3.0);
+            let vector_b = Vector3::new(2.0, 3.0, 5.0);
 
-    #[test]
-    fn test_add_vector_point() {
-        let vector_a = Tuple::vector(1.0, 2.0, 3.0);
-        let vector_b = Tuple::point(2.0, 3.0, 5.0);
+            let vector_sum = Vector3::new(3.0, 5.0, 8.0);
 
-        let vector_sum = Tuple::point(3.0, 5.0, 8.0);
+            assert_eq!(vector_a + vector_b, vector_sum);
+        }
 
-        assert_eq!(vector_a + vector_b, vector_sum);
-    }
+        // adding two points or scaling one doesn't compile at all
+        #[test]
+        fn test_add_vector_point() {
+            let vector_a = Vector3::new(1.0, 2.0, 3.0);
+            let point_b = Point3::new(2.0, 3.0, 5.0);
+
+            let point_sum = Point3::new(3.0, 5.0, 8.0);
 
-    #[test]
-    #[should_panic]
-    fn test_add_point_point() {
-        let point_a = Tuple::point(1.0, 2.0, 3.0);
-        let point_b = Tuple::point(2.0, 3.0, 5.0);
+            assert_eq!(point_b + vector_a, point_sum);
+        }
 
-        let point_sum = Tuple::point(3.0, 5.0, 8.0);
+        #[test]
+        fn test_subtract_vectors() {
+            let vector_a = Vector3::new(1.0, 2.0, 3.0);
+            let vector_b = Vector3::new(2.0, 3.0, 5.0);
 
-        assert_eq!(point_a + point_b, point_sum);
-    }
+            let vector_sum = Vector3::new(-1.0, -1.0, -2.0);
 
-    #[test]
-    fn test_subtract_vectors() {
-        let vector_a = Tuple::vector(1.0, 2.0, 3.0);
-        let vector_b = Tuple::vector(2.0, 3.0, 5.0);
+            assert_eq!(vector_a - vector_b, vector_sum);
+        }
 
-        let vector_sum = Tuple::vector(-1.0, -1.0, -2.0);
+        #[test]
+        fn test_neg_ident() {
+            let vector_a = Vector3::new(1.0, 2.0, 3.0);
+            let neg = Vector3::new(-1.0, -2.0, -3.0);
 
-        assert_eq!(vector_a - vector_b, vector_sum);
-    }
+            assert_eq!(-vector_a, neg);
+        }
 
-    #[test]
-    fn test_neg_ident() {
-        let vector_a = Tuple::vector(1.0, 2.0, 3.0);
-        let neg = Tuple::vector(-1.0, -2.0, -3.0);
+        #[test]
+        fn test_zero() {
+            let vector_a = Vector3::new(1.0, 2.0, 3.0);
+            let zero = Vector3::new(0.0, 0.0, 0.0);
 
-        assert_eq!(-vector_a, neg);
-    }
+            assert_eq!(-vector_a + vector_a, zero);
+            assert_eq!(vector_a - vector_a, zero);
+        }
 
-    #[test]
-    fn test_zero() {
-        let vector_a = Tuple::vector(1.0, 2.0, 3.0);
-        let zero = Tuple::vector(0.0, 0.0, 0.0);
+        #[test]
+        fn test_scalar_multiplication() {
+            let vector_a = Vector3::new(1.0, 2.0, 3.0);
+            let result = Vector3::new(3.0, 6.0, 9.0);
 
-        assert_eq!(-vector_a + vector_a, zero);
-        assert_eq!(vector_a - vector_a, zero);
-    }
+            assert_eq!(3.0 * vector_a, result);
+            assert_eq!(vector_a * 3.0, result);
+        }
 
-    #[test]
-    fn test_scalar_multiplication() {
-        let vector_a = Tuple::vector(1.0, 2.0, 3.0);
-        let result = Tuple::vector(3.0, 6.0, 9.0);
+        #[test]
+        fn test_scalar_div() {
+            let vector_a = Vector3::new(3.0, 6.0, 9.0);
+            let result = Vector3::new(1.0, 2.0, 3.0);
 
-        assert_eq!(3.0 * vector_a, result);
-        assert_eq!(vector_a * 3.0, result);
-    }
+            assert_eq!(vector_a / 3.0, result);
+        }
 
-    #[test]
-    #[should_panic]
-    fn test_scalar_point_mul() {
-        let _point_a = Tuple::point(1.0, 2.0, 3.0) * 3.0;
-    }
+        #[test]
+        fn test_magnitude() {
+            let vector_a = Vector3::new(3.0, 6.0, 9.0);
 
-    #[test]
-    #[should_panic]
-    fn test_scalar_point_mul_reverse() {
-        let _point_a = 3.0 * Tuple::point(1.0, 2.0, 3.0);
-    }
+            assert!(float_compare(vector_a.magnitude(), 11.224972));
+        }
 
-    #[test]
-    fn test_scalar_div() {
-        let vector_a = Tuple::vector(3.0, 6.0, 9.0);
-        let result = Tuple::vector(1.0, 2.0, 3.0);
+        #[test]
+        fn test_normalization() {
+            let vector_a = Vector3::new(3.0, 6.0, 9.0);
+            assert_eq!(vector_a.normalize().magnitude(), 1.0);
+        }
 
-        assert_eq!(vector_a / 3.0, result);
-    }
+        #[test]
+        fn test_dot() {
+            let vector_a = Vector3::new(1.0, 2.0, 3.0);
+            let vector_b = Vector3::new(2.0, 3.0, 4.0);
+            assert_eq!(vector_a.dot(&vector_b), 20.0);
+        }
 
-    #[test]
-    fn test_magnitude() {
-        let vector_a = Tuple::vector(3.0, 6.0, 9.0);
+        #[test]
+        fn test_cross() {
+            let vector_a = Vector3::new(1.0, 2.0, 3.0);
+            let vector_b = Vector3::new(2.0, 3.0, 4.0);
+            let vector_c = Vector3::new(-1.0, 2.0, -1.0);
+            assert_eq!(vector_a.cross(&vector_b), vector_c);
+        }
 
-        assert!(float_compare(vector_a.magnitude(), 11.224972));
-    }
+        #[test]
+        fn test_reflect() {
+            let v1 = Vector3::new(1., -1., 0.);
+            let n = Normal3::new(0., 1., 0.);
 
-    #[test]
-    fn test_normalization() {
-        let vector_a = Tuple::vector(3.0, 6.0, 9.0);
-        assert_eq!(vector_a.normalize().magnitude(), 1.0);
-    }
+            assert_eq!(v1.reflect(&n), Vector3::new(1., 1., 0.))
+        }
 
-    #[test]
-    fn test_dot() {
-        let vector_a = Tuple::vector(1.0, 2.0, 3.0);
-        let vector_b = Tuple::vector(2.0, 3.0, 4.0);
-        assert_eq!(vector_a.dot(&vector_b), 20.0);
-    }
+        #[test]
+        fn test_points_and_vectors() {
+            let p = Point3::new(3., 2., 1.);
+            let q = Point3::new(5., 6., 7.);
+            let v = Vector3::new(5., 6., 7.);
+
+            assert_eq!(p - q, Vector3::new(-2., -4., -6.));
+            assert_eq!(p + v, Point3::new(8., 8., 8.));
+            assert_eq!(p - v, Point3::new(-2., -4., -6.));
+            assert_eq!(v - v, Vector3::zero());
+            assert_eq!(2. * v, v * 2.);
+            // no w to sneak into the product
+            assert_eq!(v.dot(&v), 110.);
+        }
 
-    #[test]
-    fn test_cross() {
-        let vector_a = Tuple::vector(1.0, 2.0, 3.0);
-        let vector_b = Tuple::vector(2.0, 3.0, 4.0);
-        let vector_c = Tuple::vector(-1.0, 2.0, -1.0);
-        assert_eq!(vector_a.cross(&vector_b), vector_c);
-    }
+        #[test]
+        fn test_vector_ops() {
+            let a = Vector3::new(1., 2., 3.);
+            let b = Vector3::new(2., 3., 4.);
+            assert_eq!(a.cross(&b), Vector3::new(-1., 2., -1.));
+            assert!(float_compare(b.normalize().magnitude(), 1.));
+
+            let n = Normal3::new(0., 1., 0.);
+            assert_eq!(
+                Vector3::new(1., -1., 0.).reflect(&n),
+                Vector3::new(1., 1., 0.)
+            );
+            assert_eq!(n.dot(&a), 2.);
+            assert_eq!(n * 0.5, Vector3::new(0., 0.5, 0.));
+        }
 
-    #[test]
-    fn test_reflect() {
-        let v1 = Tuple::vector(1., -1., 0.);
-        let n = Tuple::vector(0., 1., 0.);
+        #[test]
+        fn test_transforming_points_and_vectors() {
+            let m = translation(5., -3., 2.);
+            // vectors don't move, points do
+            assert_eq!(&m * Point3::new(-3., 4., 5.), Point3::new(2., 1., 7.));
+            assert_eq!(
+                &m * Vector3::new(-3., 4., 5.),
+                Vector3::new(-3., 4., 5.)
+            );
+        }
 
-        assert_eq!(v1.reflect(&n), Tuple::vector(1., 1., 0.))
+        #[test]
+        fn test_transforming_normals() {
+            // the book's squashed sphere, its normals lean the other way
+            use std::f64::consts::{FRAC_1_SQRT_2, PI};
+            let (pi, h) = (PI as Float, FRAC_1_SQRT_2 as Float);
+            let m = scaling(1., 0.5, 1.) * rotation_z(pi / 5.);
+            let inverse = m.inverse();
+            let p = Point3::new(0., h, -h);
+            let local = Normal3::from_vector(&inverse * p - Point3::origin());
+            let n = local.transform(&inverse).normalize();
+            assert_eq!(n, Normal3::new(0., 0.97014, -0.24254));
+
+            // and stay at a right angle to the moved surface
+            let along = local.cross(&Vector3::new(1., 0., 0.));
+            assert!(float_compare(n.dot(&(&m * along)), 0.));
+        }
     }
 }
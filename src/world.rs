@@ -0,0 +1,905 @@
+use crate::bounds::BoundingBox;
+use crate::bvh::Bvh;
+use crate::colors::Color;
+use crate::environment::Background;
+use crate::geometries::{Shape, ShapeId, Sphere};
+use crate::intersections::{
+    prepare_computations, Computations, Intersection, Intersections,
+};
+use crate::lights::{
+    direct_lighting, lighting_samples, Light, LightSample, PointLight,
+};
+use crate::materials::Material;
+use crate::packet::{Hits, RayPacket, LANES};
+use crate::ray::Ray;
+use crate::sampling::cosine_hemisphere;
+use crate::sky::Sky;
+use crate::transformations::scaling;
+use crate::vectors::Point3;
+use std::cell::RefCell;
+
+thread_local! {
+    // hits of the ray being traced, kept around so tracing doesn't allocate
+    static SCRATCH: RefCell<Intersections> = RefCell::new(Intersections::new());
+}
+
+pub struct World {
+    // the arena every shape lives in, a shape's id is its index here
+    objects: Vec<Box<dyn Shape>>,
+    pub light: Option<Box<dyn Light>>,
+    pub background: Background,
+    // rays shot towards the background to light the scene with it, none
+    // means the background is only seen by rays that miss everything
+    pub environment_samples: usize,
+    // built on request once the objects are in place. Anything that could
+    // add, drop or move an object throws it away and the world falls back
+    // to testing all of them until it's built again
+    bvh: Option<Bvh>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        World::new()
+    }
+}
+
+impl World {
+    pub fn new() -> World {
+        World {
+            objects: vec![],
+            light: None,
+            background: Background::Color(Color::black()),
+            environment_samples: 0,
+            bvh: None,
+        }
+    }
+
+    pub fn default_world() -> World {
+        let mut s1 = Sphere::new();
+        s1.material.color = Color::new(0.8, 1.0, 0.6);
+        s1.material.diffuse = 0.7;
+        s1.material.specular = 0.2;
+
+        let mut s2 = Sphere::new();
+        s2.set_transform(scaling(0.5, 0.5, 0.5));
+
+        let mut w = World::new();
+        w.add(s1);
+        w.add(s2);
+        w.light = Some(Box::new(PointLight::new(
+            Point3::new(-10., 10., -10.),
+            Color::new(1., 1., 1.),
+        )));
+        w
+    }
+
+    // daylight from the sky model, the sun becomes the world's light and the
+    // rest of the sky lights the scene through the environment samples
+    pub fn use_sky(&mut self, sky: Sky) {
+        self.light = Some(Box::new(sky.sun()));
+        self.background = Background::Sky(sky);
+        if self.environment_samples == 0 {
+            self.environment_samples = 16;
+        }
+    }
+
+    // a group's children are added one after the other, the id of the
+    // first one is returned
+    pub fn add<S: Shape + 'static>(&mut self, shape: S) -> ShapeId {
+        self.bvh = None;
+        let first = ShapeId(self.objects.len() as u32);
+        for mut part in Box::new(shape).into_parts() {
+            part.set_id(ShapeId(self.objects.len() as u32));
+            self.objects.push(part);
+        }
+        first
+    }
+
+    pub fn objects(&self) -> &[Box<dyn Shape>] {
+        &self.objects
+    }
+
+    pub fn object(&self, id: ShapeId) -> Option<&dyn Shape> {
+        self.objects.get(id.0 as usize).map(|o| o.as_ref())
+    }
+
+    pub fn object_mut(&mut self, id: ShapeId) -> Option<&mut dyn Shape> {
+        self.bvh = None;
+        match self.objects.get_mut(id.0 as usize) {
+            Some(o) => Some(o.as_mut()),
+            None => None,
+        }
+    }
+
+    // drops the objects added last, the ones that stay keep their ids
+    pub fn truncate(&mut self, len: usize) {
+        self.bvh = None;
+        self.objects.truncate(len);
+    }
+
+    pub fn build_bvh(&mut self) {
+        let bounds: Vec<BoundingBox> = self
+            .objects
+            .iter()
+            .map(|o| o.parent_space_bounds())
+            .collect();
+        self.bvh = Some(Bvh::build(&bounds));
+    }
+
+    fn bvh(&self) -> Option<&Bvh> {
+        self.bvh.as_ref()
+    }
+
+    // every hit along the ray in no particular order. With a bvh the
+    // objects entirely behind the ray aren't tested, so only the hits in
+    // front of it are sure to be there
+    pub fn intersect_into(&self, ray: Ray, xs: &mut Intersections) {
+        match self.bvh() {
+            Some(bvh) => bvh
+                .candidates(ray, |i| self.objects[i].intersect_into(ray, xs)),
+            None => {
+                for o in &self.objects {
+                    o.intersect_into(ray, xs);
+                }
+            }
+        }
+    }
+
+    pub fn intersect(&self, ray: Ray) -> Vec<Intersection> {
+        let mut xs = Intersections::new();
+        self.intersect_into(ray, &mut xs);
+        xs.sort();
+        xs.into_vec()
+    }
+
+    // the closest hit in front of the ray
+    pub fn hit(&self, ray: Ray) -> Option<Intersection> {
+        SCRATCH.with(|scratch| {
+            let mut xs = scratch.borrow_mut();
+            let bvh = match self.bvh() {
+                Some(bvh) => bvh,
+                None => {
+                    xs.clear();
+                    self.intersect_into(ray, &mut xs);
+                    return xs.hit();
+                }
+            };
+
+            let mut closest = None;
+            bvh.closest(ray, |i, limit| {
+                xs.clear();
+                self.objects[i].intersect_into(ray, &mut xs);
+                let h = xs.hit().filter(|h| h.t < limit)?;
+                closest = Some(h);
+                Some(h.t)
+            });
+            closest
+        })
+    }
+
+    pub fn shade_hit(&self, comps: &Computations) -> Color {
+        self.shade_hit_with(comps, *comps.object.material())
+    }
+
+    // shades the hit as if the object was made out of the given material
+    pub fn shade_hit_with(&self, comps: &Computations, m: Material) -> Color {
+        // emission doesn't depend on any light, it's there even in the dark
+        let mut color = m.emissive;
+
+        if let Some(light) = &self.light {
+            color = color + self.light_hit(light.as_ref(), comps, m);
+        }
+        color + self.environment_lighting(comps, m)
+    }
+
+    // the light shaded from the same points on it that were tested for
+    // shadows, an area light would be shaded and shadowed by different
+    // jittered points otherwise
+    pub fn light_hit(
+        &self,
+        light: &dyn Light,
+        comps: &Computations,
+        m: Material,
+    ) -> Color {
+        let samples = light.samples(comps.over_point);
+        let intensity =
+            self.visibility(&samples, comps.over_point, comps.time);
+        lighting_samples(
+            m,
+            light,
+            comps.over_point,
+            comps.eyev,
+            comps.normalv,
+            &samples,
+            intensity,
+        )
+    }
+
+    fn environment_lighting(
+        &self,
+        comps: &Computations,
+        m: Material,
+    ) -> Color {
+        if self.environment_samples == 0 {
+            return Color::black();
+        }
+
+        let mut sum = Color::black();
+
+        for _ in 0..self.environment_samples {
+            let direction = cosine_hemisphere(
+                comps.normalv,
+                rand::random::<f64>(),
+                rand::random::<f64>(),
+            );
+            let cos = direction.dot(&comps.normalv);
+            let sample = LightSample {
+                direction,
+                distance: f64::INFINITY,
+            };
+            if cos <= 0. || self.occluded(comps.over_point, sample, comps.time)
+            {
+                continue;
+            }
+
+            // directions are picked with a pdf of cos / pi, which cancels
+            // out the cosine and the 1 / pi of the lambertian part
+            let radiance = self.background.color_in(direction);
+            sum = sum
+                + direct_lighting(
+                    m,
+                    radiance,
+                    direction,
+                    comps.eyev,
+                    comps.normalv,
+                ) / cos;
+        }
+
+        sum / self.environment_samples as f64
+    }
+
+    pub fn is_shadowed(&self, light: &dyn Light, point: Point3) -> bool {
+        let sample = LightSample {
+            direction: light.direction_from(point),
+            distance: light.distance_from(point),
+        };
+        self.occluded(point, sample, 0.)
+    }
+
+    // fraction of the light's samples that are visible from the point
+    pub fn intensity_at(
+        &self,
+        light: &dyn Light,
+        point: Point3,
+        time: f64,
+    ) -> f64 {
+        self.visibility(&light.samples(point), point, time)
+    }
+
+    // fraction of the given samples that are visible from the point
+    pub fn visibility(
+        &self,
+        samples: &[LightSample],
+        point: Point3,
+        time: f64,
+    ) -> f64 {
+        let visible = samples
+            .iter()
+            .filter(|&&sample| !self.occluded(point, sample, time))
+            .count();
+
+        visible as f64 / samples.len() as f64
+    }
+
+    pub fn occluded(
+        &self,
+        point: Point3,
+        sample: LightSample,
+        time: f64,
+    ) -> bool {
+        // infinitely far lights like the sun give an infinite distance here,
+        // so anything along the ray casts a shadow
+        let r = Ray::new(point, sample.direction).at_time(time);
+
+        SCRATCH.with(|scratch| {
+            let mut xs = scratch.borrow_mut();
+            let mut blocks = |o: &dyn Shape| {
+                xs.clear();
+                o.intersect_into(r, &mut xs);
+                xs.iter().any(|i| i.t >= 0. && i.t < sample.distance)
+            };
+
+            // any blocker will do, no need to find the closest one
+            match self.bvh() {
+                Some(bvh) => bvh.any(r, sample.distance, |i| {
+                    blocks(self.objects[i].as_ref())
+                }),
+                None => self.objects.iter().any(|o| blocks(o.as_ref())),
+            }
+        })
+    }
+
+    // the closest hit in front of every ray of the packet, the same ones
+    // hit would find for them one by one
+    pub fn hit_packet(&self, packet: &RayPacket) -> Hits {
+        let mut hits = [None; LANES];
+        let mask = packet.mask();
+        match self.bvh() {
+            Some(bvh) => {
+                let mut limit = [f64::INFINITY; LANES];
+                bvh.closest_packet(
+                    packet,
+                    mask,
+                    &mut limit,
+                    |i, active, limit| {
+                        self.objects[i]
+                            .intersect_packet(packet, active, &mut hits);
+                        for l in (0..LANES).filter(|&l| active[l]) {
+                            if let Some(h) = hits[l] {
+                                limit[l] = h.t;
+                            }
+                        }
+                    },
+                );
+            }
+            None => {
+                for o in &self.objects {
+                    o.intersect_packet(packet, mask, &mut hits);
+                }
+            }
+        }
+        hits
+    }
+
+    pub fn color_at(&self, ray: Ray) -> Color {
+        self.color_for_hit(ray, self.hit(ray))
+    }
+
+    // what the ray sees, given the hit it was found to have
+    pub fn color_for_hit(&self, ray: Ray, hit: Option<Intersection>) -> Color {
+        match hit {
+            None => self.background.color_in(ray.direction),
+            Some(i) => {
+                let object = self.object(i.object_id).unwrap();
+                let comps = prepare_computations(i, ray, object);
+                self.shade_hit(&comps)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::environment::Background;
+    use crate::fixtures;
+    use crate::geometries::{Plane, ShapeId, Sphere};
+    use crate::group::Group;
+    use crate::intersections::{prepare_computations, Intersection};
+    use crate::lights::{
+        AreaLight, DirectionalLight, LightSample, PointLight,
+    };
+    use crate::mesh::Mesh;
+    use crate::packet::RayPacket;
+    use crate::ray::Ray;
+    use crate::sky::Sky;
+    use crate::transformations::{rotation_y, scaling, translation};
+    use crate::utils::float_compare;
+    use crate::vectors::{Point3, Vector3};
+    use crate::world::World;
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_new_world() {
+        let w = World::new();
+        assert!(w.objects.is_empty());
+        assert!(w.light.is_none());
+    }
+
+    #[test]
+    fn test_add_hands_out_ids() {
+        let mut w = World::new();
+        assert_eq!(w.add(Plane::new()), ShapeId(0));
+        // a shape on its own isn't confused with the first one in the world
+        let loose = Sphere::new();
+        assert_eq!(loose.id, ShapeId::NONE);
+        assert_ne!(loose.id, w.objects()[0].id());
+
+        let mut g = Group::new();
+        g.add_child(Box::new(Sphere::new()));
+        g.add_child(Box::new(Sphere::new()));
+        g.set_transform(translation(0., 5., 0.));
+        // the group's children take its place
+        assert_eq!(w.add(g), ShapeId(1));
+        assert_eq!(w.add(Sphere::new()), ShapeId(3));
+        assert_eq!(w.objects().len(), 4);
+
+        let r = Ray::new(Point3::new(0., 10., 0.), Vector3::new(0., -1., 0.));
+        let h = w.hit(r).unwrap();
+        assert_eq!(h.t, 4.);
+        assert_eq!(w.object(h.object_id).unwrap().id(), h.object_id);
+        assert!(w.object(ShapeId(4)).is_none());
+
+        w.truncate(1);
+        assert_eq!(w.add(Sphere::new()), ShapeId(1));
+    }
+
+    #[test]
+    fn test_intersect_world() {
+        let w = World::default_world();
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+
+        let xs = w.intersect(r);
+        assert_eq!(xs.len(), 4);
+        assert!(float_compare(xs[0].t, 4.));
+        assert!(float_compare(xs[1].t, 4.5));
+        assert!(float_compare(xs[2].t, 5.5));
+        assert!(float_compare(xs[3].t, 6.));
+    }
+
+    #[test]
+    fn test_shade_hit() {
+        let w = World::default_world();
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        let shape = w.objects()[0].as_ref();
+        let i = Intersection {
+            object_id: shape.id(),
+            t: 4.,
+            primitive: 0,
+        };
+
+        let comps = prepare_computations(i, r, shape);
+        assert_eq!(w.shade_hit(&comps), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_shade_hit_inside() {
+        let mut w = World::default_world();
+        w.light = Some(Box::new(PointLight::new(
+            Point3::new(0., 0.25, 0.),
+            Color::new(1., 1., 1.),
+        )));
+        let r = Ray::new(Point3::new(0., 0., 0.), Vector3::new(0., 0., 1.));
+        let shape = w.objects()[1].as_ref();
+        let i = Intersection {
+            object_id: shape.id(),
+            t: 0.5,
+            primitive: 0,
+        };
+
+        let comps = prepare_computations(i, r, shape);
+        assert_eq!(w.shade_hit(&comps), Color::new(0.90498, 0.90498, 0.90498));
+    }
+
+    #[test]
+    fn test_color_at_miss() {
+        let w = World::default_world();
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 1., 0.));
+
+        assert_eq!(w.color_at(r), Color::black());
+    }
+
+    #[test]
+    fn test_color_at_hit() {
+        let w = World::default_world();
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+
+        assert_eq!(w.color_at(r), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_color_at_behind() {
+        let mut w = World::default_world();
+        w.object_mut(ShapeId(0)).unwrap().material_mut().ambient = 1.;
+        w.object_mut(ShapeId(1)).unwrap().material_mut().ambient = 1.;
+        let r = Ray::new(Point3::new(0., 0., 0.75), Vector3::new(0., 0., -1.));
+
+        assert_eq!(w.color_at(r), w.objects()[1].material().color);
+    }
+
+    #[test]
+    fn test_emissive_without_light() {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.material.emissive = Color::new(0.2, 0.9, 0.4);
+        w.add(s);
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+
+        assert_eq!(w.color_at(r), Color::new(0.2, 0.9, 0.4));
+    }
+
+    #[test]
+    fn test_emissive_adds_to_lighting() {
+        let mut w = World::default_world();
+        w.object_mut(ShapeId(0)).unwrap().material_mut().emissive =
+            Color::new(0.5, 0., 0.);
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+
+        assert_eq!(w.color_at(r), Color::new(0.88066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn test_no_shadow() {
+        let w = World::default_world();
+        let light = w.light.as_ref().unwrap().as_ref();
+
+        // nothing is collinear with point and light
+        assert!(!w.is_shadowed(light, Point3::new(0., 10., 0.)));
+        // object behind the light
+        assert!(!w.is_shadowed(light, Point3::new(-20., 20., -20.)));
+        // object behind the point
+        assert!(!w.is_shadowed(light, Point3::new(-2., 2., -2.)));
+    }
+
+    #[test]
+    fn test_shadow() {
+        let w = World::default_world();
+        let light = w.light.as_ref().unwrap().as_ref();
+
+        assert!(w.is_shadowed(light, Point3::new(10., -10., 10.)));
+    }
+
+    #[test]
+    fn test_shade_hit_in_shadow() {
+        let mut w = World::new();
+        w.light = Some(Box::new(PointLight::new(
+            Point3::new(0., 0., -10.),
+            Color::new(1., 1., 1.),
+        )));
+        w.add(Sphere::new());
+        let mut s2 = Sphere::new();
+        s2.set_transform(translation(0., 0., 10.));
+        w.add(s2);
+
+        let r = Ray::new(Point3::new(0., 0., 5.), Vector3::new(0., 0., 1.));
+        let shape = w.objects()[1].as_ref();
+        let i = Intersection {
+            object_id: shape.id(),
+            t: 4.,
+            primitive: 0,
+        };
+
+        let comps = prepare_computations(i, r, shape);
+        assert_eq!(w.shade_hit(&comps), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn test_directional_shadow_is_unbounded() {
+        let w = World::default_world();
+        let sun = DirectionalLight::new(
+            Vector3::new(-1., 0., 0.),
+            Color::new(1., 1., 1.),
+        );
+
+        // the sphere is a long way towards the sun but still in the way
+        assert!(w.is_shadowed(&sun, Point3::new(-1000., 0., 0.)));
+        assert!(!w.is_shadowed(&sun, Point3::new(-1000., 5., 0.)));
+
+        // a point light at the same spot stops at its position
+        let lamp = PointLight::new(
+            Point3::new(-500., 0., 0.),
+            Color::new(1., 1., 1.),
+        );
+        assert!(!w.is_shadowed(&lamp, Point3::new(-1000., 0., 0.)));
+    }
+
+    #[test]
+    fn test_intensity_at_point_light() {
+        let w = World::default_world();
+        let light = w.light.as_ref().unwrap().as_ref();
+
+        let cases = [
+            (Point3::new(0., 1.0001, 0.), 1.),
+            (Point3::new(-1.0001, 0., 0.), 1.),
+            (Point3::new(0., 0., -1.0001), 1.),
+            (Point3::new(0., 0., 1.0001), 0.),
+            (Point3::new(1.0001, 0., 0.), 0.),
+            (Point3::new(0., -1.0001, 0.), 0.),
+            (Point3::new(0., 0., 0.), 0.),
+        ];
+
+        for (point, expected) in cases {
+            assert!(float_compare(w.intensity_at(light, point, 0.), expected));
+        }
+    }
+
+    #[test]
+    fn test_intensity_at_area_light() {
+        let w = World::default_world();
+        let mut light = AreaLight::new(
+            Point3::new(-0.5, -0.5, -5.),
+            Vector3::new(1., 0., 0.),
+            2,
+            Vector3::new(0., 1., 0.),
+            2,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+        light.jitter = false;
+
+        let cases = [
+            (Point3::new(0., 0., 2.), 0.),
+            (Point3::new(1., -1., 2.), 0.25),
+            (Point3::new(1.5, 0., 2.), 0.5),
+            (Point3::new(1.25, 1.25, 3.), 0.75),
+            (Point3::new(0., 0., -2.), 1.),
+        ];
+
+        for (point, expected) in cases {
+            assert!(float_compare(
+                w.intensity_at(&light, point, 0.),
+                expected
+            ));
+        }
+    }
+
+    #[test]
+    fn test_soft_shadow_is_partial() {
+        let w = World::default_world();
+        let light = AreaLight::new(
+            Point3::new(-0.5, -0.5, -5.),
+            Vector3::new(1., 0., 0.),
+            8,
+            Vector3::new(0., 1., 0.),
+            8,
+            Color::new(1., 1., 1.),
+        )
+        .unwrap();
+
+        // in the penumbra some cells are always visible and some never are
+        let intensity = w.intensity_at(&light, Point3::new(1.5, 0., 2.), 0.);
+        assert!(intensity > 0.2 && intensity < 0.8);
+    }
+
+    #[test]
+    fn test_color_at_miss_background() {
+        let mut w = World::default_world();
+        w.background = Background::Gradient {
+            zenith: Color::new(0., 0., 1.),
+            horizon: Color::new(1., 1., 1.),
+            ground: Color::new(0., 1., 0.),
+        };
+
+        let up = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 1., 0.));
+        assert_eq!(w.color_at(up), Color::new(0., 0., 1.));
+    }
+
+    #[test]
+    fn test_environment_lighting() {
+        let mut w = World::new();
+        let mut s = Sphere::new();
+        s.material.ambient = 0.;
+        s.material.specular = 0.;
+        w.add(s);
+        w.background = Background::Color(Color::new(1., 1., 1.));
+
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        assert_eq!(w.color_at(r), Color::black());
+
+        // a white sky all around reflects the diffuse color back
+        w.environment_samples = 16;
+        assert_eq!(w.color_at(r), Color::new(0.9, 0.9, 0.9));
+    }
+
+    #[test]
+    fn test_environment_lighting_occluded() {
+        let mut w = World::new();
+        let mut floor = Sphere::new();
+        floor.material.ambient = 0.;
+        floor.material.specular = 0.;
+        w.add(floor);
+
+        // a big lid right above the sphere blocks most of its sky
+        let mut lid = Sphere::new();
+        lid.set_transform(translation(0., 0., -3.) * scaling(5., 5., 1.));
+        w.add(lid);
+        w.background = Background::Color(Color::new(1., 1., 1.));
+        w.environment_samples = 64;
+
+        let r = Ray::new(Point3::new(0., 0., -1.5), Vector3::new(0., 0., 1.));
+        let shaded = w.color_at(r).max_component();
+        assert!(shaded < 0.5);
+    }
+
+    #[test]
+    fn test_use_sky() {
+        let mut w = World::default_world();
+        let sky = Sky::new(PI / 4., 0., 3.);
+        let sun_direction = sky.sun_direction();
+        let zenith = sky.color_in(Vector3::new(0., 1., 0.));
+        w.use_sky(sky);
+
+        let light = w.light.as_ref().unwrap();
+        assert_eq!(
+            light.direction_from(Point3::new(0., 0., 0.)),
+            sun_direction
+        );
+        assert!(w.environment_samples > 0);
+
+        let up = Ray::new(Point3::new(0., 5., 0.), Vector3::new(0., 1., 0.));
+        assert_eq!(w.color_at(up), zenith);
+    }
+
+    fn random_world(rng: &mut StdRng) -> World {
+        let mut w = World::new();
+        w.add(Plane::new());
+        let (min, max) =
+            (Point3::new(-10., 1., -10.), Point3::new(10., 11., 10.));
+        for s in fixtures::spheres(rng, 100, min, max, (0.1, 0.9)) {
+            w.add(s);
+        }
+
+        let mut g = Group::new();
+        for i in 0..5 {
+            let mut s = Sphere::new();
+            s.set_transform(translation(i as f64 * 2., 0., 0.));
+            g.add_child(Box::new(s));
+        }
+        g.set_transform(translation(-4., 3., 0.) * rotation_y(0.5));
+        w.add(g);
+        w
+    }
+
+    fn random_ray(rng: &mut StdRng) -> Ray {
+        let (min, max) =
+            (Point3::new(-15., 0., -15.), Point3::new(15., 15., 15.));
+        fixtures::ray_in(rng, min, max)
+    }
+
+    fn ahead(ints: Vec<Intersection>) -> Vec<f64> {
+        ints.iter().map(|i| i.t).filter(|&t| t >= 0.).collect()
+    }
+
+    #[test]
+    fn test_bvh_matches_brute_force() {
+        let mut rng = fixtures::rng();
+        let mut w = random_world(&mut rng);
+        let rays: Vec<Ray> = (0..500).map(|_| random_ray(&mut rng)).collect();
+        let expected: Vec<Vec<f64>> =
+            rays.iter().map(|&r| ahead(w.intersect(r))).collect();
+
+        let hits: Vec<Option<f64>> =
+            rays.iter().map(|&r| w.hit(r).map(|h| h.t)).collect();
+
+        w.build_bvh();
+        for (i, r) in rays.iter().enumerate() {
+            assert_eq!(ahead(w.intersect(*r)), expected[i]);
+            assert_eq!(w.hit(*r).map(|h| h.t), hits[i]);
+        }
+    }
+
+    #[test]
+    fn test_bvh_shadows_match_brute_force() {
+        let mut rng = fixtures::rng();
+        let mut w = random_world(&mut rng);
+        let samples: Vec<(Point3, LightSample)> = (0..500)
+            .map(|_| {
+                let r = random_ray(&mut rng);
+                let sample = LightSample {
+                    direction: r.direction,
+                    distance: rng.gen::<f64>() * 20.,
+                };
+                (r.origin, sample)
+            })
+            .collect();
+        let expected: Vec<bool> = samples
+            .iter()
+            .map(|&(p, sample)| w.occluded(p, sample, 0.))
+            .collect();
+
+        w.build_bvh();
+        for (&(p, sample), &e) in samples.iter().zip(expected.iter()) {
+            assert_eq!(w.occluded(p, sample, 0.), e);
+        }
+    }
+
+    fn packet_world(rng: &mut StdRng) -> World {
+        let mut w = random_world(rng);
+        let mut moving = Sphere::new();
+        moving.set_motion(translation(0., 2., 0.), translation(3., 2., 0.));
+        w.add(moving);
+
+        let mut positions = vec![];
+        let mut triangles = vec![];
+        for i in 0..10u32 {
+            let x = i as f64 * 1.5 - 7.;
+            positions.extend([[x, 0.5, 4.], [x + 1., 0.5, 4.], [x, 2., 5.]]);
+            triangles.push([3 * i, 3 * i + 1, 3 * i + 2]);
+        }
+        w.add(Mesh::new(positions, triangles).unwrap());
+        w
+    }
+
+    // what a camera shoots, neighbouring rays from the same eye
+    fn coherent_rays(rng: &mut StdRng, n: usize) -> Vec<Ray> {
+        let eye = Point3::new(0., 6., -20.);
+        let start = fixtures::point_in(
+            rng,
+            Point3::new(-10., 0., 0.),
+            Point3::new(10., 6., 0.),
+        );
+        (0..n)
+            .map(|i| {
+                let target = start + Vector3::new(i as f64 * 0.05, 0., 0.);
+                Ray::new(eye, (target - eye).normalize())
+                    .at_time(rng.gen::<f64>())
+            })
+            .collect()
+    }
+
+    fn assert_packet_matches(w: &World, rays: &[Ray]) {
+        let hits = w.hit_packet(&RayPacket::new(rays).unwrap());
+        for (l, &r) in rays.iter().enumerate() {
+            match (hits[l], w.hit(r)) {
+                (None, None) => {}
+                (Some(a), Some(b)) => {
+                    assert!(float_compare(a.t, b.t));
+                    assert_eq!(a.object_id, b.object_id);
+                    assert_eq!(a.primitive, b.primitive);
+                }
+                (a, b) => panic!("lane {}: {:?} vs {:?}", l, a, b),
+            }
+        }
+    }
+
+    #[test]
+    fn test_packets_match_single_rays() {
+        let mut rng = fixtures::rng();
+        let mut w = packet_world(&mut rng);
+        for width in [1, 4, 8] {
+            for _ in 0..100 {
+                assert_packet_matches(&w, &coherent_rays(&mut rng, width));
+            }
+        }
+
+        w.build_bvh();
+        for width in [1, 4, 8] {
+            for _ in 0..100 {
+                assert_packet_matches(&w, &coherent_rays(&mut rng, width));
+                // rays that go every which way split up right away
+                let scattered: Vec<Ray> =
+                    (0..width).map(|_| random_ray(&mut rng)).collect();
+                assert_packet_matches(&w, &scattered);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stale_bvh_is_ignored() {
+        let mut w = World::default_world();
+        w.build_bvh();
+        let mut s = Sphere::new();
+        s.set_transform(translation(0., 0., -3.) * scaling(0.5, 0.5, 0.5));
+        w.add(s);
+
+        let r = Ray::new(Point3::new(0., 0., -5.), Vector3::new(0., 0., 1.));
+        assert_eq!(w.intersect(r).len(), 6);
+
+        // swapping the last object keeps the count the same
+        w.build_bvh();
+        w.truncate(2);
+        let mut s = Sphere::new();
+        s.set_transform(translation(0., 0., 5.));
+        w.add(s);
+        assert_eq!(w.intersect(r).len(), 6);
+    }
+
+    #[test]
+    fn test_moving_object_drops_bvh() {
+        let mut w = World::default_world();
+        w.build_bvh();
+        let r = Ray::new(Point3::new(5., 0., -5.), Vector3::new(0., 0., 1.));
+        assert!(w.hit(r).is_none());
+
+        w.object_mut(ShapeId(1))
+            .unwrap()
+            .transform_by(&translation(5., 0., 0.));
+        assert!(float_compare(w.hit(r).unwrap().t, 4.5));
+        assert!(w.is_shadowed(
+            w.light.as_ref().unwrap().as_ref(),
+            Point3::new(5.5, -0.5, 0.5)
+        ));
+    }
+}